@@ -1,17 +1,29 @@
 use std::fmt;
 
-use regex::Regex;
-use utils::{Grid, StaticGrid};
+use utils::{
+    cpu::{
+        isa::{Instruction, InstructionSet, X},
+        Cpu, CycleState, Observer,
+    },
+    ocr, Grid, StaticGrid,
+};
+
+/// Sums the signal strength during the 20th cycle and every 40 after that
+#[derive(Default)]
+struct SignalStrength {
+    sum: isize,
+}
 
-enum Operation {
-    AddX(isize),
-    Noop,
+impl Observer for SignalStrength {
+    fn on_cycle(&mut self, state: &CycleState) {
+        if (state.cycle + 20).is_multiple_of(40) {
+            self.sum += state.cycle as isize * state.register(X);
+        }
+    }
 }
 
-struct Cpu {
-    x_register: isize,
-    cycles: usize,
-    answer: isize,
+/// Draws one pixel per cycle, lit if the 3 pixel wide sprite (centered on X) covers it
+struct Crt {
     display: StaticGrid<Pixel>,
 }
 
@@ -32,96 +44,71 @@ impl fmt::Display for Pixel {
     }
 }
 
-impl Cpu {
+impl Crt {
     pub fn new() -> Self {
-        Cpu {
-            x_register: 1,
-            cycles: 0,
-            answer: 0,
+        Crt {
             display: StaticGrid::new(6, 40),
         }
     }
+}
 
-    fn calc_answer1(&mut self) {
-        if (self.cycles + 20) % 40 == 0 {
-            self.answer += self.cycles as isize * self.x_register;
-        };
-    }
-
-    fn update_display(&mut self) {
-        if (self.x_register..self.x_register + 3).contains(&((self.cycles % 40) as isize)) {
-            let c = self
-                .display
-                .get_cell_mut(self.cycles as isize % 40, self.cycles as isize / 40)
-                .unwrap();
-            c.value = '#';
-        }
-    }
-
-    pub fn tick(&mut self, num_cycles: usize) {
-        for _ in 0..num_cycles {
-            self.cycles += 1;
-            self.update_display();
-            self.calc_answer1();
-        }
-    }
-
-    pub fn execute_operation(&mut self, operation: &Operation) {
-        match operation {
-            Operation::AddX(amount) => {
-                self.tick(2);
-                self.x_register += amount;
-            }
-            Operation::Noop => {
-                self.tick(1);
+impl Observer for Crt {
+    fn on_cycle(&mut self, state: &CycleState) {
+        let position = state.cycle as isize - 1;
+        let (col, row) = (position % 40, position / 40);
+        let x = state.register(X);
+        if (x - 1..=x + 1).contains(&col) {
+            if let Some(c) = self.display.get_cell_mut(col, row) {
+                c.value = '#';
             }
         }
     }
 }
 
-fn parser(s: String) -> Vec<Operation> {
-    let re = Regex::new(r"(addx|noop) ?(-?\d+)?").unwrap();
-    s.split('\n')
-        .into_iter()
-        .map(|l| {
-            let caps = re.captures(l).unwrap();
-            match caps.get(1).unwrap().as_str() {
-                "addx" => Operation::AddX(caps.get(2).unwrap().as_str().parse().unwrap()),
-                "noop" => Operation::Noop,
-                _ => panic!("This should not happen..."),
-            }
-        })
-        .collect()
+fn parser(s: String) -> Vec<Instruction> {
+    InstructionSet::handheld()
+        .parse(&s)
+        .unwrap_or_else(|e| panic!("Bad program: {e}"))
 }
 
-fn solve(operations: &Vec<Operation>) -> isize {
-    let mut cpu = Cpu::new();
-    for operation in operations {
-        cpu.execute_operation(operation);
-    }
-    cpu.answer
+fn solve(program: &[Instruction]) -> isize {
+    let isa = InstructionSet::handheld();
+    let mut signal = SignalStrength::default();
+    let mut cpu = Cpu::new(&isa, program.to_vec());
+    cpu.add_observer(&mut signal);
+    cpu.run();
+    drop(cpu);
+    signal.sum
 }
 
-fn solve2(operations: &Vec<Operation>) {
-    let mut cpu = Cpu::new();
-    for operation in operations {
-        cpu.execute_operation(operation);
-    }
-    println!("Solution 2 (8 characters below):");
-    println!("{}", cpu.display);
+fn render(program: &[Instruction]) -> StaticGrid<Pixel> {
+    let isa = InstructionSet::handheld();
+    let mut crt = Crt::new();
+    let mut cpu = Cpu::new(&isa, program.to_vec());
+    cpu.add_observer(&mut crt);
+    cpu.run();
+    drop(cpu);
+    crt.display
+}
+
+fn solve2(program: &[Instruction]) -> String {
+    let display = render(program);
+    ocr::recognize(&display, |p| p.value == '#')
+        .unwrap_or_else(|e| panic!("Unreadable display: {e}\n{display}"))
 }
 
 fn main() {
-    let operations = utils::load_puzzle_data(10, parser);
-    let x_register_sum = solve(&operations);
+    let program = utils::load_puzzle_data(10, parser);
+    let x_register_sum = solve(&program);
     println!("Solution 1: CPU signal strength sum: {x_register_sum}");
 
-    solve2(&operations);
+    let letters = solve2(&program);
+    println!("Solution 2: The CRT displays: {letters}");
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::{parser, solve};
+    use crate::{parser, render, solve};
 
     #[test]
     fn test_puzzle() {
@@ -129,4 +116,20 @@ mod tests {
         let solution = solve(&test_data);
         assert_eq!(solution, 13140);
     }
+
+    #[test]
+    fn test_puzzle2() {
+        let test_data = utils::load_puzzle_test(10, parser);
+        let display = render(&test_data);
+        assert_eq!(
+            display.to_string(),
+            "##..##..##..##..##..##..##..##..##..##..
+###...###...###...###...###...###...###.
+####....####....####....####....####....
+#####.....#####.....#####.....#####.....
+######......######......######......####
+#######.......#######.......#######.....
+"
+        );
+    }
 }
@@ -1,22 +1,56 @@
-use std::collections::VecDeque;
+use std::{collections::VecDeque, fmt, rc::Rc};
 
-enum OperationValue {
-    Constant(usize),
-    Old
+use num_bigint::BigUint;
+use utils::{
+    expr::{Arithmetic, EvalError, Expr},
+    residue::Residues,
+};
+
+/// A type worry levels can be kept in
+trait Worry: Arithmetic + Clone + PartialEq {
+    /// `tests` are every monkey's test value
+    fn start(value: u64, _tests: &Rc<[u64]>) -> Result<Self, EvalError> {
+        Self::constant(value)
+    }
+
+    /// Worry is kept mod this (when not dividing by 3), so it doesn't grow forever
+    fn modulus(tests: &[u64]) -> Result<Option<Self>, EvalError> {
+        tests
+            .iter()
+            .try_fold(Self::constant(1)?, |product, test| product.mul(&Self::constant(*test)?))
+            .map(Some)
+    }
+
+    fn divisible_by(&self, test: u64) -> Result<bool, EvalError> {
+        Ok(self.rem(&Self::constant(test)?)? == Self::constant(0)?)
+    }
 }
 
-/// Operation is (first OP second)
-enum Operation {
-    Add((OperationValue, OperationValue)),
-    Mul((OperationValue, OperationValue)),
+impl Worry for u64 {}
+
+impl Worry for u128 {}
+
+impl Worry for BigUint {}
+
+impl Worry for Residues {
+    fn start(value: u64, tests: &Rc<[u64]>) -> Result<Self, EvalError> {
+        Ok(Residues::new(value, tests.clone()))
+    }
+
+    /// Residues are already bounded by the tests
+    fn modulus(_tests: &[u64]) -> Result<Option<Self>, EvalError> {
+        Ok(None)
+    }
 }
 
-struct Monkey {
-    /// List of items, where usize is the current worry level
-    items: VecDeque<usize>,
-    operation: Operation,
+/// `I` is what an item is held as, just its worry level until a Game gives it an id
+struct Monkey<I = u64> {
+    /// List of items, in the order they'll be inspected
+    items: VecDeque<I>,
+    /// new = operation, in terms of `old`
+    operation: Expr,
     /// Divisible by test_value
-    test_value: usize,
+    test_value: u64,
     /// If test true: throw to monkey
     truth_monkey: usize,
     /// If test false: throw to monkey
@@ -24,66 +58,166 @@ struct Monkey {
     num_inspected_items: usize
 }
 
-struct Game {
-    monkeys: Vec<Monkey>,
+impl Monkey {
+    /// Numbers the items from `next_id` on
+    fn with_worry<W: Worry>(self, tests: &Rc<[u64]>, next_id: &mut usize) -> Result<Monkey<Item<W>>, EvalError> {
+        let mut items = VecDeque::new();
+        for worry in self.items {
+            items.push_back(Item { id: *next_id, worry: W::start(worry, tests)? });
+            *next_id += 1;
+        }
+        Ok(Monkey {
+            items,
+            operation: self.operation,
+            test_value: self.test_value,
+            truth_monkey: self.truth_monkey,
+            falsity_monkey: self.falsity_monkey,
+            num_inspected_items: self.num_inspected_items
+        })
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+struct Item<W> {
+    /// Items are numbered in the order they're listed, starting with monkey 0's
+    id: usize,
+    worry: W
+}
+
+/// One monkey inspecting and throwing one item
+#[derive(Clone, Debug, PartialEq)]
+struct Throw<W> {
+    round: usize,
+    monkey: usize,
+    item: usize,
+    worry_before: W,
+    worry_after: W,
+    to: usize
+}
+
+/// What the monkeys look like at the end of a round
+#[derive(Clone, Debug, PartialEq)]
+struct RoundSnapshot<W> {
+    round: usize,
+    /// Each monkey's queue
+    items: Vec<Vec<Item<W>>>,
+    /// Each monkey's total inspections so far
+    inspected: Vec<usize>
+}
+
+impl<W: fmt::Display> RoundSnapshot<W> {
+    /// Like the puzzle's "After round 1, the monkeys are holding items with these worry levels:"
+    fn holding_summary(&self) -> String {
+        self.items
+            .iter()
+            .enumerate()
+            .map(|(ndx, items)| {
+                let worries: Vec<String> = items.iter().map(|item| item.worry.to_string()).collect();
+                if worries.is_empty() {
+                    format!("Monkey {ndx}:\n")
+                } else {
+                    format!("Monkey {ndx}: {}\n", worries.join(", "))
+                }
+            })
+            .collect()
+    }
+}
+
+impl<W> RoundSnapshot<W> {
+    /// Like the puzzle's "== After round 20 ==" blocks
+    fn inspection_summary(&self) -> String {
+        let mut summary = format!("== After round {} ==\n", self.round);
+        for (ndx, inspected) in self.inspected.iter().enumerate() {
+            summary.push_str(&format!("Monkey {ndx} inspected items {inspected} times.\n"));
+        }
+        summary
+    }
+}
+
+/// The `k` monkeys with the most inspections, as (monkey, inspections), busiest first
+fn top_k(inspected: &[usize], k: usize) -> Vec<(usize, usize)> {
+    let mut ranked: Vec<(usize, usize)> = inspected.iter().copied().enumerate().collect();
+    // Stable, so ties stay in monkey order
+    ranked.sort_by_key(|&(_, inspected)| std::cmp::Reverse(inspected));
+    ranked.truncate(k);
+    ranked
+}
+
+struct Game<W> {
+    monkeys: Vec<Monkey<Item<W>>>,
     current_round: usize,
     divide_by_three: bool,
-    divisor: usize
+    divisor: Option<W>,
+    /// Every throw, if enabled
+    log: Option<Vec<Throw<W>>>,
+    /// The state after every round, if enabled
+    snapshots: Option<Vec<RoundSnapshot<W>>>
 }
 
-impl Game {
-    fn play(&mut self, num_rounds: usize) {
-        self.divisor = self.monkeys.iter().fold(1, |a,m| a * m.test_value);
+impl<W: Worry> Game<W> {
+    fn new(monkeys: Vec<Monkey>, divide_by_three: bool) -> Result<Self, EvalError> {
+        let tests: Rc<[u64]> = monkeys.iter().map(|m| m.test_value).collect();
+        let mut next_id = 0;
+        Ok(Game {
+            monkeys: monkeys.into_iter().map(|m| m.with_worry(&tests, &mut next_id)).collect::<Result<_, _>>()?,
+            current_round: 1,
+            divide_by_three,
+            divisor: if divide_by_three { None } else { W::modulus(&tests)? },
+            log: None,
+            snapshots: None
+        })
+    }
+
+    fn take_snapshot(&self) -> RoundSnapshot<W> {
+        RoundSnapshot {
+            round: self.current_round,
+            items: self.monkeys.iter().map(|m| m.items.iter().cloned().collect()).collect(),
+            inspected: self.monkeys.iter().map(|m| m.num_inspected_items).collect()
+        }
+    }
+
+    /// The `k` busiest monkeys so far, as (monkey, inspections)
+    fn top_monkeys(&self, k: usize) -> Vec<(usize, usize)> {
+        let inspected: Vec<usize> = self.monkeys.iter().map(|m| m.num_inspected_items).collect();
+        top_k(&inspected, k)
+    }
+
+    fn play(&mut self, num_rounds: usize) -> Result<(), EvalError> {
         for _ in 0..num_rounds {
             for monkey_ndx in 0..self.monkeys.len() {
-                self.run_turn(monkey_ndx);
+                self.run_turn(monkey_ndx)?;
+            }
+            if self.snapshots.is_some() {
+                let snapshot = self.take_snapshot();
+                self.snapshots.get_or_insert_with(Vec::new).push(snapshot);
             }
             self.current_round += 1;
         }
+        Ok(())
     }
 
-    fn run_turn(&mut self, monkey_ndx: usize) {
+    fn run_turn(&mut self, monkey_ndx: usize) -> Result<(), EvalError> {
         // 0. To avoid borrowing issues, save passes for later
-        let mut passes: Vec<(usize, usize)> = vec![];
+        let mut passes: Vec<(usize, Item<W>)> = vec![];
         // 1. Inspect item
         let monkey = self.monkeys.get_mut(monkey_ndx).unwrap();
         while let Some(mut item) = monkey.items.pop_front() {
             monkey.num_inspected_items += 1;
+            let worry_before = item.worry.clone();
             // 2. Perform monkey's operation, modifying the worry level
-            item = match &monkey.operation {
-                Operation::Add(op_values) => {
-                    let val1 = match op_values.0 {
-                        OperationValue::Constant(val) => val,
-                        OperationValue::Old => item,
-                    };
-                    let val2 = match op_values.1 {
-                        OperationValue::Constant(val) => val,
-                        OperationValue::Old => item,
-                    };
-                    val1 + val2
-                },
-                Operation::Mul(op_values) => {
-                    let val1 = match op_values.0 {
-                        OperationValue::Constant(val) => val,
-                        OperationValue::Old => item,
-                    };
-                    let val2 = match op_values.1 {
-                        OperationValue::Constant(val) => val,
-                        OperationValue::Old => item,
-                    };
-                    val1 * val2
-                }
-            };
+            item.worry = monkey
+                .operation
+                .eval(&|name| (name == "old").then(|| item.worry.clone()))?;
             // 3. Monkey bored...
             if self.divide_by_three {
                 // 3a. Divide worry by 3
-                item /= 3;
-            } else {
+                item.worry = item.worry.div(&W::constant(3)?)?;
+            } else if let Some(divisor) = &self.divisor {
                 // 3b. Mod worry by the product of the tests
-                item %= self.divisor;
+                item.worry = item.worry.rem(divisor)?;
             }
             // 4. Perform monkey's test
-            let pass_monkey_ndx = match (item % monkey.test_value) == 0 {
+            let pass_monkey_ndx = match item.worry.divisible_by(monkey.test_value)? {
                 true => {
                     monkey.truth_monkey
                 },
@@ -91,13 +225,50 @@ impl Game {
                     monkey.falsity_monkey
                 },
             };
+            if let Some(log) = self.log.as_mut() {
+                log.push(Throw {
+                    round: self.current_round,
+                    monkey: monkey_ndx,
+                    item: item.id,
+                    worry_before,
+                    worry_after: item.worry.clone(),
+                    to: pass_monkey_ndx
+                });
+            }
             // 5. Pass the item (Save)
             passes.push((pass_monkey_ndx, item));
-            //pass_monkey.items.push_back(item);
         }
         for pass in passes {
             self.monkeys.get_mut(pass.0).unwrap().items.push_back(pass.1);
         }
+        Ok(())
+    }
+}
+
+// Tracing, for checking the simulation against the puzzle's examples
+impl<W: Worry> Game<W> {
+    /// Start recording every throw
+    fn enable_log(&mut self) {
+        self.log.get_or_insert_with(Vec::new);
+    }
+
+    fn log(&self) -> &[Throw<W>] {
+        self.log.as_deref().unwrap_or_default()
+    }
+
+    /// Start recording a snapshot after every round
+    fn enable_snapshots(&mut self) {
+        self.snapshots.get_or_insert_with(Vec::new);
+    }
+
+    /// The snapshot taken after `round`, needs enable_snapshots
+    fn snapshot(&self, round: usize) -> Option<&RoundSnapshot<W>> {
+        self.snapshots.as_ref()?.iter().find(|s| s.round == round)
+    }
+
+    /// The `k` busiest monkeys after `round`, needs enable_snapshots
+    fn top_monkeys_after(&self, round: usize, k: usize) -> Option<Vec<(usize, usize)>> {
+        self.snapshot(round).map(|s| top_k(&s.inspected, k))
     }
 }
 
@@ -112,31 +283,18 @@ fn parser(s: String) -> Vec<Monkey> {
     */
     //let items_re = Regex::new(r"Starting items: (?:(\d+),?)+").unwrap();
     s.split("\n\n")
-        .into_iter()
         .map(|monkey| {
             let mut line = monkey.split('\n');
             // Skip Monkey X: line
             line.next();
             let items = line.next().unwrap().split_once(": ").unwrap().1.split(", ").map(|n| n.parse().unwrap()).collect();
-            let operation_str_vec: Vec<_> = line.next().unwrap().split_once(": ").unwrap().1.split_once("= ").unwrap().1.split(' ').collect();
-            let op_val_1 = match operation_str_vec.first() {
-                Some(&"old") => OperationValue::Old,
-                Some(&n) => OperationValue::Constant(n.parse().unwrap()),
-                _ => panic!("This should not happen")
-            };
-            let op_val_2 = match operation_str_vec.get(2) {
-                Some(&"old") => OperationValue::Old,
-                Some(&n) => OperationValue::Constant(n.parse().unwrap()),
-                _ => panic!("This should not happen")
-            };
-            let operation = match *operation_str_vec.get(1).unwrap() {
-                "*" => Operation::Mul((op_val_1, op_val_2)),
-                "+" => Operation::Add((op_val_1, op_val_2)),
-                _ => panic!("This should not happen")
-            };
-            let test_value = line.next().unwrap().split(' ').last().unwrap().parse().unwrap();
-            let truth_monkey = line.next().unwrap().split(' ').last().unwrap().parse().unwrap();
-            let falsity_monkey = line.next().unwrap().split(' ').last().unwrap().parse().unwrap();
+            let operation_str = line.next().unwrap().split_once("= ").unwrap().1;
+            let operation = Expr::parse(operation_str)
+                .unwrap_or_else(|e| panic!("Bad operation '{operation_str}': {e}"))
+                .fold();
+            let test_value = line.next().unwrap().split(' ').next_back().unwrap().parse().unwrap();
+            let truth_monkey = line.next().unwrap().split(' ').next_back().unwrap().parse().unwrap();
+            let falsity_monkey = line.next().unwrap().split(' ').next_back().unwrap().parse().unwrap();
             Monkey {
                 items,
                 operation,
@@ -149,52 +307,121 @@ fn parser(s: String) -> Vec<Monkey> {
         .collect()
 }
 
-fn solve(monkeys: Vec<Monkey>, num_rounds: usize, divide_by_three: bool) -> usize {
-    let mut game = Game {
-        monkeys,
-        current_round: 1,
-        divide_by_three,
-        divisor: 1
-    };
-    game.play(num_rounds);
-    let mut ultimate = 0;
-    let mut penultimate = 0;
-    for monkey in game.monkeys {
-        if monkey.num_inspected_items > ultimate {
-            penultimate = ultimate;
-            ultimate = monkey.num_inspected_items;
-        } else if monkey.num_inspected_items > penultimate {
-            penultimate = monkey.num_inspected_items;
-        }
-    }
-    ultimate * penultimate
+fn solve<W: Worry>(monkeys: Vec<Monkey>, num_rounds: usize, divide_by_three: bool) -> Result<usize, EvalError> {
+    let mut game = Game::<W>::new(monkeys, divide_by_three)?;
+    game.play(num_rounds)?;
+    Ok(game.top_monkeys(2).iter().map(|(_, inspected)| inspected).product())
 }
 
 fn main() {
     let monkeys = utils::load_puzzle_data(11, parser);
-    let officer_farva = solve(monkeys, 20, true);
+    let officer_farva = solve::<u64>(monkeys, 20, true).unwrap_or_else(|e| panic!("Worry got out of hand: {e}"));
     println!("Solution 1: There is a monkey shenanigan level of: {officer_farva}");
 
     let monkeys = utils::load_puzzle_data(11, parser);
-    let the_business = solve(monkeys, 10000, false);
+    // Residues can't overflow, however big the tests are
+    let the_business = solve::<Residues>(monkeys, 10000, false).unwrap_or_else(|e| panic!("Worry got out of hand: {e}"));
     println!("Solution 2: After 10,000 rounds, there is a monkey business level of: {the_business}");
+
+    if std::env::args().any(|arg| arg == "--trace") {
+        trace(utils::load_puzzle_data(11, parser), 20).unwrap_or_else(|e| panic!("Worry got out of hand: {e}"));
+    }
+}
+
+/// Prints every throw and the state after every round of part 1
+fn trace(monkeys: Vec<Monkey>, num_rounds: usize) -> Result<(), EvalError> {
+    let mut game = Game::<u64>::new(monkeys, true)?;
+    game.enable_log();
+    game.enable_snapshots();
+    game.play(num_rounds)?;
+    for throw in game.log() {
+        println!(
+            "Round {}: monkey {} threw item {} ({} -> {}) to monkey {}",
+            throw.round, throw.monkey, throw.item, throw.worry_before, throw.worry_after, throw.to
+        );
+    }
+    for round in 1..=num_rounds {
+        let Some(snapshot) = game.snapshot(round) else {
+            continue;
+        };
+        println!("After round {round}, the monkeys are holding items with these worry levels:");
+        print!("{}", snapshot.holding_summary());
+    }
+    if let Some(snapshot) = game.snapshot(num_rounds) {
+        print!("{}", snapshot.inspection_summary());
+    }
+    if let Some(top) = game.top_monkeys_after(num_rounds, 2) {
+        println!("Busiest monkeys: {top:?}");
+    }
+    Ok(())
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::{parser, solve};
+    use num_bigint::BigUint;
+    use utils::{
+        expr::{BinOp, EvalError},
+        residue::Residues,
+    };
+
+    use crate::{parser, solve, Game, Throw};
 
     #[test]
     fn test_puzzle() {
         let test_data = utils::load_puzzle_test(11, parser);
-        let solution = solve(test_data, 20, true);
-        assert_eq!(solution, 10605);
+        let solution = solve::<u64>(test_data, 20, true);
+        assert_eq!(solution, Ok(10605));
+
+        // Residues can't be divided
+        let test_data = utils::load_puzzle_test(11, parser);
+        let solution = solve::<Residues>(test_data, 20, true);
+        assert_eq!(solution, Err(EvalError::Unsupported(BinOp::Div)));
     }
 
     #[test]
     fn test_puzzle2() {
         let test_data = utils::load_puzzle_test(11, parser);
-        let solution = solve(test_data, 10000, false);
-        assert_eq!(solution, 2713310158);
+        assert_eq!(solve::<u64>(test_data, 10000, false), Ok(2713310158));
+        let test_data = utils::load_puzzle_test(11, parser);
+        assert_eq!(solve::<u128>(test_data, 10000, false), Ok(2713310158));
+        let test_data = utils::load_puzzle_test(11, parser);
+        assert_eq!(solve::<BigUint>(test_data, 10000, false), Ok(2713310158));
+        let test_data = utils::load_puzzle_test(11, parser);
+        assert_eq!(solve::<Residues>(test_data, 10000, false), Ok(2713310158));
+    }
+
+    #[test]
+    fn test_overflow() {
+        // Without keeping worry mod the tests, old * old soon overflows a u64
+        let test_data = utils::load_puzzle_test(11, parser);
+        let mut game = Game::<u64>::new(test_data, false).unwrap();
+        game.divisor = None;
+        assert_eq!(game.play(20), Err(EvalError::Overflow));
+    }
+
+    #[test]
+    fn test_log_and_snapshots() {
+        let test_data = utils::load_puzzle_test(11, parser);
+        let mut game = Game::<u64>::new(test_data, true).unwrap();
+        game.enable_log();
+        game.enable_snapshots();
+        game.play(20).unwrap();
+        assert_eq!(
+            game.log()[0],
+            Throw { round: 1, monkey: 0, item: 0, worry_before: 79, worry_after: 500, to: 3 }
+        );
+        assert_eq!(game.log().len(), 101 + 95 + 7 + 105);
+        assert_eq!(
+            game.snapshot(1).unwrap().holding_summary(),
+            "Monkey 0: 20, 23, 27, 26\nMonkey 1: 2080, 25, 167, 207, 401, 1046\nMonkey 2:\nMonkey 3:\n"
+        );
+        assert_eq!(
+            game.snapshot(20).unwrap().inspection_summary(),
+            "== After round 20 ==\nMonkey 0 inspected items 101 times.\nMonkey 1 inspected items 95 times.\n\
+             Monkey 2 inspected items 7 times.\nMonkey 3 inspected items 105 times.\n"
+        );
+        assert_eq!(game.top_monkeys(2), vec![(3, 105), (0, 101)]);
+        assert_eq!(game.top_monkeys_after(1, 3), Some(vec![(3, 5), (1, 4), (2, 3)]));
+        assert_eq!(game.snapshot(21), None);
     }
 }
@@ -1,8 +1,8 @@
 extern crate pathfinding;
 
-use utils::StaticGrid;
+use utils::{region, Grid, StaticGrid};
 
-use pathfinding::prelude::{astar, astar_bag};
+use pathfinding::prelude::astar;
 
 struct Board {
     grid: StaticGrid<Pos>,
@@ -97,7 +97,7 @@ fn solve(board: &Board) -> usize {
                 .take(1)
                 .filter(|&c| c.height <= p.height + 1)
                 .collect();
-            let v = vec![north.iter(), south.iter(), east.iter(), west.iter()];
+            let v = [north.iter(), south.iter(), east.iter(), west.iter()];
             let b: Vec<(Pos, usize)> = v
                 .iter()
                 .flat_map(|it| it.clone())
@@ -113,50 +113,17 @@ fn solve(board: &Board) -> usize {
 
 fn solve2(board: &Board) -> usize {
     // This time, start at the End, and find all paths to 'a', use shortest path
-    let goal: Pos = board.end_pos;
-    let results = astar_bag(
-        &board.end_pos,
-        |p| {
-            let north: Vec<&Pos> = board
-                .grid
-                .direction_iter_at(p.x, p.y, utils::Direction::North)
-                .skip(1)
-                .take(1)
-                .filter(|&c| p.height <= c.height + 1)
-                .collect();
-            let south: Vec<&Pos> = board
-                .grid
-                .direction_iter_at(p.x, p.y, utils::Direction::South)
-                .skip(1)
-                .take(1)
-                .filter(|&c| p.height <= c.height + 1)
-                .collect();
-            let east: Vec<&Pos> = board
-                .grid
-                .direction_iter_at(p.x, p.y, utils::Direction::East)
-                .skip(1)
-                .take(1)
-                .filter(|&c| p.height <= c.height + 1)
-                .collect();
-            let west: Vec<&Pos> = board
-                .grid
-                .direction_iter_at(p.x, p.y, utils::Direction::West)
-                .skip(1)
-                .take(1)
-                .filter(|&c| p.height <= c.height + 1)
-                .collect();
-            let v = vec![north.iter(), south.iter(), east.iter(), west.iter()];
-            let b: Vec<(Pos, usize)> = v
-                .iter()
-                .flat_map(|it| it.clone())
-                .map(|&f| (*f, 1_usize))
-                .collect();
-            b
-        },
-        |p| p.distance(&goal) / 3,
-        |p| p.height == 1,
+    let distances = region::reachable_distances(
+        &board.grid,
+        (board.end_pos.x, board.end_pos.y),
+        |from, to| from.height <= to.height + 1,
     );
-    results.expect("No shortest path").1
+    distances
+        .into_iter()
+        .filter(|&((x, y), _)| board.grid.get_cell(x, y).unwrap().height == 1)
+        .map(|(_, distance)| distance)
+        .min()
+        .expect("No shortest path")
 }
 
 fn main() {
@@ -0,0 +1,277 @@
+use std::{cmp::Ordering, error::Error, fmt, slice, str::FromStr};
+
+/// Equality is the puzzle's, so 1, [1] and [[1]] are all equal
+#[derive(Clone, Debug)]
+pub enum Packet {
+    Int(u64),
+    List(Vec<Packet>),
+}
+
+impl Ord for Packet {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (self, other) {
+            (Packet::Int(a), Packet::Int(b)) => a.cmp(b),
+            // Slices compare item by item, then by length, just like the puzzle
+            (Packet::List(a), Packet::List(b)) => a.cmp(b),
+            (Packet::Int(_), Packet::List(b)) => slice::from_ref(self).cmp(b),
+            (Packet::List(a), Packet::Int(_)) => a.as_slice().cmp(slice::from_ref(other)),
+        }
+    }
+}
+
+impl PartialOrd for Packet {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for Packet {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Packet {}
+
+impl fmt::Display for Packet {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Packet::Int(value) => write!(f, "{value}"),
+            Packet::List(items) => {
+                write!(f, "[")?;
+                for (ndx, item) in items.iter().enumerate() {
+                    if ndx > 0 {
+                        write!(f, ",")?;
+                    }
+                    write!(f, "{item}")?;
+                }
+                write!(f, "]")
+            }
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum ParseError {
+    /// Byte offset of the unexpected char
+    UnexpectedChar {
+        offset: usize,
+        found: char,
+    },
+    UnexpectedEnd {
+        offset: usize,
+    },
+    IntegerTooLarge {
+        offset: usize,
+    },
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParseError::UnexpectedChar { offset, found } => {
+                write!(f, "unexpected '{found}' at offset {offset}")
+            }
+            ParseError::UnexpectedEnd { offset } => {
+                write!(f, "packet ends early at offset {offset}")
+            }
+            ParseError::IntegerTooLarge { offset } => {
+                write!(f, "integer at offset {offset} is too large")
+            }
+        }
+    }
+}
+
+impl Error for ParseError {}
+
+/// Recursive descent straight over the bytes:
+///  packet = integer | '[' (packet (',' packet)*)? ']'
+struct Parser<'a> {
+    s: &'a str,
+    offset: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn peek(&self) -> Option<u8> {
+        self.s.as_bytes().get(self.offset).copied()
+    }
+
+    fn unexpected(&self) -> ParseError {
+        match self.s[self.offset..].chars().next() {
+            Some(found) => ParseError::UnexpectedChar {
+                offset: self.offset,
+                found,
+            },
+            None => ParseError::UnexpectedEnd {
+                offset: self.offset,
+            },
+        }
+    }
+
+    fn packet(&mut self) -> Result<Packet, ParseError> {
+        match self.peek() {
+            Some(b'[') => self.list(),
+            Some(b) if b.is_ascii_digit() => self.integer(),
+            _ => Err(self.unexpected()),
+        }
+    }
+
+    fn list(&mut self) -> Result<Packet, ParseError> {
+        self.offset += 1;
+        let mut items = vec![];
+        if self.peek() == Some(b']') {
+            self.offset += 1;
+            return Ok(Packet::List(items));
+        }
+        loop {
+            items.push(self.packet()?);
+            match self.peek() {
+                Some(b',') => self.offset += 1,
+                Some(b']') => {
+                    self.offset += 1;
+                    return Ok(Packet::List(items));
+                }
+                _ => return Err(self.unexpected()),
+            }
+        }
+    }
+
+    fn integer(&mut self) -> Result<Packet, ParseError> {
+        let start = self.offset;
+        let mut value: u64 = 0;
+        while let Some(b) = self.peek().filter(u8::is_ascii_digit) {
+            value = value
+                .checked_mul(10)
+                .and_then(|v| v.checked_add((b - b'0') as u64))
+                .ok_or(ParseError::IntegerTooLarge { offset: start })?;
+            self.offset += 1;
+        }
+        Ok(Packet::Int(value))
+    }
+}
+
+impl FromStr for Packet {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parser = Parser { s, offset: 0 };
+        let packet = parser.packet()?;
+        match parser.peek() {
+            None => Ok(packet),
+            Some(_) => Err(parser.unexpected()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cmp::Ordering;
+
+    use proptest::prelude::*;
+
+    use super::{Packet, ParseError};
+
+    fn packet() -> impl Strategy<Value = Packet> {
+        // Small numbers, so packets often tie
+        (0..4u64)
+            .prop_map(Packet::Int)
+            .prop_recursive(4, 32, 4, |inner| {
+                prop::collection::vec(inner, 0..4).prop_map(Packet::List)
+            })
+    }
+
+    fn p(s: &str) -> Packet {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn test_ord() {
+        let pairs: Vec<(Packet, Packet)> = utils::load_puzzle_test(13, |s| {
+            s.split("\n\n")
+                .map(|pair| {
+                    let (left, right) = pair.trim().split_once('\n').unwrap();
+                    (p(left), p(right))
+                })
+                .collect()
+        });
+        let in_order: Vec<bool> = pairs.iter().map(|(l, r)| l < r).collect();
+        assert_eq!(
+            in_order,
+            [true, true, false, true, false, true, false, false]
+        );
+
+        assert_eq!(p("1"), p("[[1]]"));
+        assert!(p("[]") < p("[[]]"));
+        assert!(p("[[1],2]") > p("[1]"));
+    }
+
+    proptest! {
+        #[test]
+        fn prop_total_order(a in packet(), b in packet(), c in packet()) {
+            prop_assert_eq!(a.cmp(&a), Ordering::Equal);
+            prop_assert_eq!(a.cmp(&b), b.cmp(&a).reverse());
+            prop_assert_eq!(a == b, a.cmp(&b) == Ordering::Equal);
+            if a <= b && b <= c {
+                prop_assert!(a <= c);
+            }
+            let mut sorted = [a, b, c];
+            sorted.sort();
+            prop_assert!(sorted.windows(2).all(|w| w[0] <= w[1]));
+        }
+
+        #[test]
+        fn prop_round_trip(a in packet()) {
+            let text = a.to_string();
+            prop_assert_eq!(text.parse::<Packet>().map(|b| b.to_string()), Ok(text));
+        }
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(
+            "[1,[],[2,[3]]]".parse(),
+            Ok(Packet::List(vec![
+                Packet::Int(1),
+                Packet::List(vec![]),
+                Packet::List(vec![
+                    Packet::Int(2),
+                    Packet::List(vec![Packet::Int(3)])
+                ]),
+            ]))
+        );
+        for line in utils::load_puzzle_test(13, |s| s).lines() {
+            if !line.is_empty() {
+                assert_eq!(line.parse::<Packet>().unwrap().to_string(), line);
+            }
+        }
+
+        let err = |s: &str| s.parse::<Packet>().unwrap_err();
+        assert_eq!(err("[1,2"), ParseError::UnexpectedEnd { offset: 4 });
+        assert_eq!(err(""), ParseError::UnexpectedEnd { offset: 0 });
+        assert_eq!(
+            err("[1,,2]"),
+            ParseError::UnexpectedChar {
+                offset: 3,
+                found: ','
+            }
+        );
+        assert_eq!(
+            err("[1] "),
+            ParseError::UnexpectedChar {
+                offset: 3,
+                found: ' '
+            }
+        );
+        assert_eq!(
+            err("[1,\"a\"]"),
+            ParseError::UnexpectedChar {
+                offset: 3,
+                found: '"'
+            }
+        );
+        assert_eq!(
+            err("[99999999999999999999]"),
+            ParseError::IntegerTooLarge { offset: 1 }
+        );
+    }
+}
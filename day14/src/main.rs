@@ -1,6 +1,11 @@
+#![cfg_attr(all(test, feature = "bench"), feature(test))]
+
+mod sim;
+
 use std::fmt::Display;
 
-use utils::{Direction, DynamicGrid, Grid, Point, Growable};
+use sim::{Bottom, SandSim};
+use utils::{DynamicGrid, Point, Growable};
 
 struct Cave<CellType> {
     grid: DynamicGrid<CellType>,
@@ -9,13 +14,6 @@ struct Cave<CellType> {
 #[derive(Clone, Default)]
 struct CaveSpace {
     is_rock: bool,
-    is_sand: bool,
-}
-
-impl CaveSpace {
-    pub fn is_blocked(&self) -> bool {
-        self.is_rock || self.is_sand
-    }
 }
 
 impl<CellType> Cave<CellType> {
@@ -29,13 +27,7 @@ impl Display for CaveSpace {
         write!(
             f,
             "{:>1}",
-            if self.is_rock {
-                '#'
-            } else if self.is_sand {
-                'o'
-            } else {
-                '.'
-            }
+            if self.is_rock { '#' } else { '.' }
         )
     }
 }
@@ -79,126 +71,31 @@ fn parser(s: String) -> Cave<CaveSpace> {
     cave
 }
 
-fn solve(cave: &mut Cave<CaveSpace>, has_abyss: bool) -> usize {
-    let mut count = 0;
-    let mut new_sand = true;
-    let mut x: isize = 500;
-    let mut y: isize = 0;
-    loop {
-        if new_sand {
-            count += 1;
-        }
-        // New Piece of sand drops
-        (x, y) = match new_sand {
-            true => (500, 0),
-            false => (x, y),
-        };
-        let mut sand_lost_to_abyss = false;
-        loop {
-            let start_x = x;
-            let start_y = y;
-            let mut iter = cave
-                .grid
-                .direction_iter(start_x, start_y, Direction::South)
-                .enumerate()
-                .peekable();
-            while let Some((ndx, _)) = iter.next() {
-                // Sand tries to fall straight down
-                y = start_y + ndx as isize + 1;
-                if let Some((_, c)) = iter.peek() {
-                    // If the tile immediately below is blocked (by rock or sand),
-                    if c.is_blocked() {
-                        // the unit of sand attempts to instead move diagonally one step down and to the left
-                        x -= 1;
-                        let Some(c) = cave.grid.get_cell(x, y) else {
-                            // Into the abyss!
-                            sand_lost_to_abyss = true;
-                            break;
-                        };
-                        // If that tile is blocked
-                        if c.is_blocked() {
-                            // the unit of sand attempts to instead move diagonally one step down and to the right
-                            x += 2;
-                            let Some(c) = cave.grid.get_cell(x, y) else {
-                                // Into the abyss!
-                                sand_lost_to_abyss = true;
-                                break;
-                            };
-                            // If that tile is blocked
-                            if c.is_blocked() {
-                                // Stays where it is
-                                x -= 1;
-                                y -= 1;
-                            }
-                        }
-                    }
-                } else {
-                    // Fell off the edge of the world (no more known spaces below)
-                    sand_lost_to_abyss = true;
-                }
-                if x != start_x || (y == start_y + ndx as isize) {
-                    // Moved columns or sand did not move, restart iterator
-                    break;
-                }
-            }
-            if sand_lost_to_abyss {
-                break;
-            }
-            if x == start_x {
-                // Did not move, put it to rest
-                let b = cave.grid.get_cell_mut(x, y).unwrap();
-                b.is_sand = true;
-                break;
-            }
-        }
-        new_sand = true;
-        if !has_abyss && sand_lost_to_abyss {
-            // Add column and continue falling
-            let last = cave.grid.last_cell_coord();
-            let b = cave.grid.get_cell_or_add_mut(x, last.1);
-            b.is_rock = true;
-            new_sand = false;
-            if y > last.1 {
-                y = last.1 - 1;
-                if x > 500 {
-                    x -= 1;
-                } else {
-                    x += 1;
-                }
-            }
-        } else if sand_lost_to_abyss {
-            // Floated off -- lost one, and done
-            count -= 1;
-            break;
-        } else if y == 0 {
-            // Filled them all!
-            break;
-        }
-    }
-    count
+fn solve(cave: Cave<CaveSpace>) -> usize {
+    SandSim::new(cave).with_bottom(Bottom::Abyss).run_with_path()
 }
 
-fn solve2(cave: &mut Cave<CaveSpace>) -> usize {
+fn solve2(cave: Cave<CaveSpace>) -> usize {
     // There's actually a floor!
-    // Add it...
-    let bottom = cave.grid.last_cell_coord();
-    let y = bottom.1 + 2;
-    for x in cave.grid.first_cell_coord().0..=bottom.0 {
-        let c = cave.grid.get_cell_or_add_mut(x, y);
-        c.is_rock = true;
-    }
-
-    solve(cave, false)
+    SandSim::new(cave).with_bottom(Bottom::Floor).run_with_path()
 }
 
 fn main() {
-    let mut cave = utils::load_puzzle_data(14, parser);
-    let mut pieces_of_sand = solve(&mut cave, true);
+    let cave = utils::load_puzzle_data(14, parser);
+    let pieces_of_sand = solve(cave);
     println!("Solution 1: There were {pieces_of_sand} pieces of sand that came to rest.");
 
-    pieces_of_sand += solve2(&mut cave);
-    print!("{}",cave.grid);
+    let cave = utils::load_puzzle_data(14, parser);
+    let pieces_of_sand = solve2(cave);
     println!("Solution 2: There were {pieces_of_sand} pieces of sand that came to rest.");
+
+    let args: Vec<String> = std::env::args().collect();
+    if args.iter().any(|arg| arg == "--draw") {
+        // The cave once the sand has filled up to the source
+        let mut sim = SandSim::new(utils::load_puzzle_data(14, parser)).with_bottom(Bottom::Floor);
+        sim.run_with_path();
+        print!("{sim}");
+    }
 }
 
 #[cfg(test)]
@@ -207,20 +104,52 @@ mod tests {
 
     #[test]
     fn test_puzzle() {
-        let mut test_data = utils::load_puzzle_test(14, parser);
-        let solution = solve(&mut test_data, true);
+        let test_data = utils::load_puzzle_test(14, parser);
+        let solution = solve(test_data);
         assert_eq!(solution, 24);
     }
 
     #[test]
     fn test_puzzle2() {
-        let mut test_data = utils::load_puzzle_test(14, parser);
-        let mut solution = solve(&mut test_data, true);
-        assert_eq!(solution, 24);
-        print!("{}",test_data.grid);
-
-        solution += solve2(&mut test_data);
+        let test_data = utils::load_puzzle_test(14, parser);
+        let solution = solve2(test_data);
         assert_eq!(solution, 93);
-        print!("{}",test_data.grid);
+    }
+}
+
+// Needs nightly: cargo +nightly bench -p day14 --features bench
+#[cfg(all(test, feature = "bench"))]
+mod benches {
+    extern crate test;
+
+    use test::Bencher;
+
+    use crate::{
+        parser,
+        sim::{Bottom, SandSim},
+    };
+
+    const INPUT: &str = include_str!("../../puzzles/day14.txt");
+
+    // Parsing is part of every bench, so they can be compared
+    fn cave_with_floor() -> SandSim {
+        SandSim::new(parser(INPUT.to_string())).with_bottom(Bottom::Floor)
+    }
+
+    #[bench]
+    fn bench_per_grain(b: &mut Bencher) {
+        b.iter(|| cave_with_floor().run_to_rest());
+    }
+
+    #[bench]
+    fn bench_path(b: &mut Bencher) {
+        b.iter(|| cave_with_floor().run_with_path());
+    }
+
+    #[bench]
+    fn bench_fill_count(b: &mut Bencher) {
+        let expected = cave_with_floor().run_with_path();
+        assert_eq!(cave_with_floor().fill_count(), Some(expected));
+        b.iter(|| cave_with_floor().fill_count());
     }
 }
@@ -0,0 +1,414 @@
+// The puzzle only needs part of the engine, the tests and benches use the rest
+#![allow(dead_code)]
+
+use std::{collections::HashSet, fmt};
+
+use utils::Grid;
+
+use crate::{Cave, CaveSpace};
+
+/// (x, y), y grows downwards
+pub type Pos = (isize, isize);
+
+/// Down, then down-left, then down-right
+pub const PUZZLE_MOVES: [Pos; 3] = [(0, 1), (-1, 1), (1, 1)];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Bottom {
+    /// Sand falling below the lowest rock is lost
+    Abyss,
+    /// An endless floor two below the lowest rock
+    Floor,
+}
+
+/// Endless solid areas, worked out from the position instead of stored in the cave
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Boundary {
+    /// Solid at and below this y
+    Floor(isize),
+    /// Solid at and left of this x
+    LeftWall(isize),
+    /// Solid at and right of this x
+    RightWall(isize),
+}
+
+impl Boundary {
+    pub fn blocks(&self, (x, y): Pos) -> bool {
+        match *self {
+            Boundary::Floor(floor) => y >= floor,
+            Boundary::LeftWall(wall) => x <= wall,
+            Boundary::RightWall(wall) => x >= wall,
+        }
+    }
+}
+
+/// What one call to `SandSim::step` did
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Step {
+    Spawned(Pos),
+    Fell(Pos),
+    Rested(Pos),
+    /// Fell past the lowest rock; its source is done
+    Lost(Pos),
+}
+
+/// Drops sand grain by grain from the sources, taking turns, until every source is done:
+/// either blocked by sand (or rock) or losing its sand to the abyss
+pub struct SandSim {
+    /// Only the rock from the scan
+    cave: Cave<CaveSpace>,
+    sand: HashSet<Pos>,
+    boundaries: Vec<Boundary>,
+    sources: Vec<Pos>,
+    /// Tried in order, the first open one is taken
+    moves: Vec<Pos>,
+    lowest_rock: isize,
+    /// The grain in the air, and the source it came from
+    falling: Option<(usize, Pos)>,
+    next_source: usize,
+    done: Vec<bool>,
+    resting: usize,
+}
+
+impl SandSim {
+    /// The puzzle's setup: one source at 500,0, the usual moves and an abyss
+    pub fn new(cave: Cave<CaveSpace>) -> Self {
+        let lowest_rock = cave.grid.last_cell_coord().1;
+        SandSim {
+            cave,
+            sand: HashSet::new(),
+            boundaries: vec![],
+            sources: vec![(500, 0)],
+            moves: PUZZLE_MOVES.to_vec(),
+            lowest_rock,
+            falling: None,
+            next_source: 0,
+            done: vec![false],
+            resting: 0,
+        }
+    }
+
+    pub fn with_sources(mut self, sources: Vec<Pos>) -> Self {
+        self.done = vec![false; sources.len()];
+        self.sources = sources;
+        self
+    }
+
+    pub fn with_moves(mut self, moves: Vec<Pos>) -> Self {
+        self.moves = moves;
+        self
+    }
+
+    pub fn with_bottom(mut self, bottom: Bottom) -> Self {
+        self.boundaries.retain(|b| !matches!(b, Boundary::Floor(_)));
+        match bottom {
+            Bottom::Abyss => self,
+            Bottom::Floor => {
+                let floor = self.lowest_rock + 2;
+                self.with_boundary(Boundary::Floor(floor))
+            }
+        }
+    }
+
+    pub fn with_boundary(mut self, boundary: Boundary) -> Self {
+        self.boundaries.push(boundary);
+        self
+    }
+
+    pub fn cave(&self) -> &Cave<CaveSpace> {
+        &self.cave
+    }
+
+    /// Grains that came to rest so far
+    pub fn resting(&self) -> usize {
+        self.resting
+    }
+
+    pub fn sand(&self) -> &HashSet<Pos> {
+        &self.sand
+    }
+
+    fn is_rock(&self, (x, y): Pos) -> bool {
+        self.cave.grid.get_cell(x, y).is_some_and(|c| c.is_rock)
+            || self.boundaries.iter().any(|b| b.blocks((x, y)))
+    }
+
+    pub fn is_blocked(&self, pos: Pos) -> bool {
+        self.sand.contains(&pos) || self.is_rock(pos)
+    }
+
+    // Without a floor, nothing below the lowest rock can stop a grain
+    fn is_lost(&self, (_, y): Pos) -> bool {
+        y > self.lowest_rock && !self.boundaries.iter().any(|b| matches!(b, Boundary::Floor(_)))
+    }
+
+    // The next source that isn't done, marking blocked ones as done
+    fn spawn(&mut self) -> Option<(usize, Pos)> {
+        for _ in 0..self.sources.len() {
+            let ndx = self.next_source;
+            self.next_source = (ndx + 1) % self.sources.len();
+            if self.done[ndx] {
+                continue;
+            }
+            if self.is_blocked(self.sources[ndx]) {
+                self.done[ndx] = true;
+                continue;
+            }
+            return Some((ndx, self.sources[ndx]));
+        }
+        None
+    }
+
+    /// Spawns a grain or moves the falling one by one cell. None once every source is done.
+    pub fn step(&mut self) -> Option<Step> {
+        let Some((source, (x, y))) = self.falling else {
+            self.falling = self.spawn();
+            return self.falling.map(|(_, pos)| Step::Spawned(pos));
+        };
+        let next = self
+            .moves
+            .iter()
+            .map(|(dx, dy)| (x + dx, y + dy))
+            .find(|pos| !self.is_blocked(*pos));
+        match next {
+            Some(pos) if self.is_lost(pos) => {
+                self.falling = None;
+                self.done[source] = true;
+                Some(Step::Lost(pos))
+            }
+            Some(pos) => {
+                self.falling = Some((source, pos));
+                Some(Step::Fell(pos))
+            }
+            None => {
+                self.sand.insert((x, y));
+                self.falling = None;
+                self.resting += 1;
+                Some(Step::Rested((x, y)))
+            }
+        }
+    }
+
+    /// Runs one grain to the end. Where it rested, or None if it was lost or nothing could
+    /// be dropped.
+    pub fn drop_one(&mut self) -> Option<Pos> {
+        loop {
+            match self.step()? {
+                Step::Rested(pos) => return Some(pos),
+                Step::Lost(_) => return None,
+                Step::Spawned(_) | Step::Fell(_) => (),
+            }
+        }
+    }
+
+    /// Drops sand until every source is done, returning how many grains are resting
+    pub fn run_to_rest(&mut self) -> usize {
+        while self.step().is_some() {}
+        self.resting
+    }
+
+    /// Same result as run_to_rest with a single source, but faster: a grain comes to rest
+    /// at the end of the last grain's path, so the next one can carry on from just before
+    /// it instead of falling all the way from the source
+    pub fn run_with_path(&mut self) -> usize {
+        assert_eq!(self.sources.len(), 1, "The path is only shared by one source's sand");
+        // Finish the grain in the air the usual way first
+        while self.falling.is_some() {
+            self.step();
+        }
+        let source = self.sources[0];
+        let mut path = vec![];
+        while !self.done[0] {
+            let Some(&(x, y)) = path.last() else {
+                if self.is_blocked(source) {
+                    self.done[0] = true;
+                } else {
+                    path.push(source);
+                }
+                continue;
+            };
+            let next = self
+                .moves
+                .iter()
+                .map(|(dx, dy)| (x + dx, y + dy))
+                .find(|pos| !self.is_blocked(*pos));
+            match next {
+                Some(pos) if self.is_lost(pos) => self.done[0] = true,
+                Some(pos) => path.push(pos),
+                None => {
+                    self.sand.insert((x, y));
+                    self.resting += 1;
+                    path.pop();
+                }
+            }
+        }
+        self.resting
+    }
+
+    /// How much sand run_to_rest would leave, without dropping any. With a floor every cell
+    /// sand can reach ends up full, so this counts them a row at a time. None unless there's
+    /// a floor, one source, no sand yet and every move goes down exactly one row.
+    pub fn fill_count(&self) -> Option<usize> {
+        let floor = self.boundaries.iter().find_map(|b| match b {
+            Boundary::Floor(y) => Some(*y),
+            _ => None,
+        })?;
+        if self.sources.len() != 1 || !self.sand.is_empty() || self.moves.iter().any(|m| m.1 != 1)
+        {
+            return None;
+        }
+
+        let (source_x, source_y) = self.sources[0];
+        let mut row: Vec<isize> = vec![source_x];
+        row.retain(|x| !self.is_blocked((*x, source_y)));
+        let mut count = row.len();
+        for y in source_y + 1..floor {
+            if row.is_empty() {
+                break;
+            }
+            let mut next: Vec<isize> = row
+                .iter()
+                .flat_map(|x| self.moves.iter().map(move |(dx, _)| x + dx))
+                .filter(|x| !self.is_blocked((*x, y)))
+                .collect();
+            next.sort_unstable();
+            next.dedup();
+            count += next.len();
+            row = next;
+        }
+        Some(count)
+    }
+}
+
+/// Draws the puzzle's pictures: + for sources, # for rock, o for sand. Fits the rock, sand
+/// and sources, plus the floor if there is one.
+impl fmt::Display for SandSim {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let (first_x, first_y) = self.cave.grid.first_cell_coord();
+        let (last_x, last_y) = self.cave.grid.last_cell_coord();
+        let floor = self.boundaries.iter().filter_map(|b| match b {
+            Boundary::Floor(y) => Some(*y),
+            _ => None,
+        });
+        let points = self.sand.iter().chain(&self.sources).copied();
+        let (min_x, max_x) = points
+            .clone()
+            .fold((first_x, last_x), |(min, max), (x, _)| (min.min(x), max.max(x)));
+        let (min_y, max_y) = points
+            .map(|(_, y)| y)
+            .chain(floor)
+            .fold((first_y, last_y), |(min, max), y| (min.min(y), max.max(y)));
+        for y in min_y..=max_y {
+            for x in min_x..=max_x {
+                let c = if self.sand.contains(&(x, y)) {
+                    'o'
+                } else if self.sources.contains(&(x, y)) {
+                    '+'
+                } else if self.is_rock((x, y)) {
+                    '#'
+                } else {
+                    '.'
+                };
+                write!(f, "{c}")?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parser;
+
+    use super::{Bottom, Boundary, SandSim, Step};
+
+    const PUZZLE: &str = include_str!("../puzzle.txt");
+
+    fn example() -> SandSim {
+        SandSim::new(utils::load_puzzle_test(14, parser))
+    }
+
+    // The `rows` lines of the puzzle's picture starting with `first`
+    fn picture(first: &str, rows: usize) -> Vec<&str> {
+        let start = PUZZLE.rfind(first).unwrap();
+        PUZZLE[start..].lines().take(rows).collect()
+    }
+
+    #[test]
+    fn test_steps() {
+        let mut sim = example();
+        assert_eq!(sim.step(), Some(Step::Spawned((500, 0))));
+        assert_eq!(sim.step(), Some(Step::Fell((500, 1))));
+        assert_eq!(sim.drop_one(), Some((500, 8)));
+        assert_eq!(sim.drop_one(), Some((499, 8)));
+        assert_eq!(sim.drop_one(), Some((501, 8)));
+        assert_eq!(sim.run_to_rest(), 24);
+        assert_eq!(sim.drop_one(), None);
+        assert_eq!(sim.resting(), 24);
+        assert!(!sim.is_blocked((500, 0)));
+        assert_eq!(
+            sim.to_string().lines().collect::<Vec<_>>(),
+            picture("......+...\n..........\n......o...", 10)
+        );
+
+        let mut sim = example().with_bottom(Bottom::Floor);
+        assert_eq!(sim.run_to_rest(), 93);
+        assert!(sim.is_blocked((500, 0)));
+        assert!(sim.is_blocked((1000, 11)));
+        // The puzzle's picture has two spare columns each side
+        let expected: Vec<&str> = picture("............o....", 12)
+            .iter()
+            .map(|row| &row[2..row.len() - 2])
+            .collect();
+        assert_eq!(sim.to_string().lines().collect::<Vec<_>>(), expected);
+        // Only the scan's rock is stored
+        assert_eq!(sim.cave().grid.num_rows, 10);
+
+        assert_eq!(example().with_bottom(Bottom::Floor).fill_count(), Some(93));
+        let mut sim = example().with_bottom(Bottom::Floor);
+        assert_eq!(sim.run_with_path(), 93);
+        let mut sim = example();
+        assert_eq!(sim.drop_one(), Some((500, 8)));
+        sim.step();
+        assert_eq!(sim.run_with_path(), 24);
+        assert_eq!(sim.drop_one(), None);
+    }
+
+    #[test]
+    fn test_config() {
+        // Straight down only piles up above the floor of the shelf at y 9
+        let mut sim = example().with_moves(vec![(0, 1)]);
+        assert_eq!(sim.run_to_rest(), 9);
+
+        // A second source off to the side pours into the abyss straight away
+        let mut sim = example().with_sources(vec![(500, 0), (600, 0)]);
+        assert_eq!(sim.step(), Some(Step::Spawned((500, 0))));
+        assert_eq!(sim.drop_one(), Some((500, 8)));
+        assert_eq!(sim.drop_one(), None);
+        assert_eq!(sim.run_to_rest(), 24);
+
+        assert_eq!(example().fill_count(), None);
+        let mut sim = example().with_moves(vec![(0, 1)]).with_bottom(Bottom::Floor);
+        assert_eq!(sim.fill_count(), Some(9));
+        assert_eq!(sim.run_with_path(), 9);
+        assert_eq!(sim.fill_count(), None);
+
+        // Two sources sharing a floor
+        let mut sim = example()
+            .with_sources(vec![(500, 0), (490, 0)])
+            .with_bottom(Bottom::Floor);
+        let mut single = example().with_bottom(Bottom::Floor);
+        assert!(sim.run_to_rest() > single.run_to_rest());
+
+        // A wall against the shelf's left end piles sand up against it, until it spills
+        // down the gap between the shelf and the right wall
+        let mut sim = example()
+            .with_boundary(Boundary::LeftWall(493))
+            .with_boundary(Boundary::RightWall(504));
+        assert_eq!(sim.run_to_rest(), 47);
+        assert!(sim.sand().contains(&(494, 8)));
+        let mut sim = sim.with_bottom(Bottom::Floor).with_bottom(Bottom::Abyss);
+        assert_eq!(sim.drop_one(), None);
+    }
+}
@@ -1,5 +1,9 @@
-use std::collections::HashSet;
+use std::{collections::HashSet, ops::RangeInclusive};
 
+use utils::{
+    geom::{self, Diamond},
+    Point2D,
+};
 use vector2d::Vector2D;
 
 #[derive(Debug)]
@@ -9,6 +13,19 @@ struct Sensor {
     distance: isize,
 }
 
+impl Sensor {
+    /// The area this sensor has ruled out
+    fn area(&self) -> Diamond {
+        Diamond::new(
+            Point2D {
+                x: self.pos.x,
+                y: self.pos.y,
+            },
+            self.distance,
+        )
+    }
+}
+
 fn parser(s: String) -> Vec<Sensor> {
     //Sensor at x=2, y=18: closest beacon is at x=-2, y=15
     let re = regex::Regex::new(
@@ -31,7 +48,7 @@ fn parser(s: String) -> Vec<Sensor> {
         let sensor = Sensor {
             pos: Vector2D { x: sx, y: sy },
             closest_beacon: Vector2D { x: bx, y: by },
-            distance: manhattan_distance(sx, sy, bx, by),
+            distance: geom::manhattan_distance(Point2D { x: sx, y: sy }, Point2D { x: bx, y: by }),
         };
 
         sensors.push(sensor);
@@ -39,114 +56,60 @@ fn parser(s: String) -> Vec<Sensor> {
     sensors
 }
 
-fn manhattan_distance(a_x: isize, a_y: isize, b_x: isize, b_y: isize) -> isize {
-    (a_x - b_x).abs() + (a_y - b_y).abs()
+/// The x ranges on row y that are within some sensor's range, merged
+fn row_coverage(sensors: &[Sensor], y: isize) -> Vec<RangeInclusive<isize>> {
+    geom::merge_intervals(
+        sensors
+            .iter()
+            .filter_map(|sensor| sensor.area().row_interval(y))
+            .collect(),
+    )
+}
+
+/// The x ranges in `within` on row y that no sensor covers
+fn row_gaps(
+    sensors: &[Sensor],
+    y: isize,
+    within: RangeInclusive<isize>,
+) -> Vec<RangeInclusive<isize>> {
+    geom::interval_gaps(&row_coverage(sensors, y), within)
 }
 
 fn solve(sensors: &[Sensor], filter_row: isize) -> usize {
-    let mut affected_cols = HashSet::new();
-    for sensor in sensors {
-        // Filter sensors that don't affect filter_row
-        if sensor.pos.y + sensor.distance < filter_row
-            || sensor.pos.y - sensor.distance > filter_row
-        {
-            continue;
-        }
-
-        for x in sensor.pos.x - sensor.distance..=sensor.pos.x + sensor.distance {
-            if sensors
-                .iter()
-                .find_map(|c| {
-                    if (c.closest_beacon.x == x && c.closest_beacon.y == filter_row)
-                        || (c.pos.x == x && c.pos.y == filter_row)
-                    {
-                        Some(x)
-                    } else {
-                        None
-                    }
-                })
-                .is_some()
-            {
-                // Don't overwrite signals or beacons
-                continue;
-            }
-            if manhattan_distance(sensor.pos.x, sensor.pos.y, x, filter_row) <= sensor.distance {
-                affected_cols.insert(x);
-            }
-        }
-    }
-    affected_cols.len()
+    let coverage = row_coverage(sensors, filter_row);
+    let covered: usize = coverage.iter().map(|r| (r.end() - r.start() + 1) as usize).sum();
+    // Beacons on the row are inside the coverage, as their sensors reach them
+    let beacons: HashSet<isize> = sensors
+        .iter()
+        .filter(|s| s.closest_beacon.y == filter_row)
+        .map(|s| s.closest_beacon.x)
+        .collect();
+    covered - beacons.len()
 }
 
 fn solve2(sensors: &[Sensor], max_val: isize) -> isize {
     // x * 4_000_000 + y
-    let mut x = 0;
-    let mut y = 0;
-
-    // Explore the boundaries of each sensor
-    'outer: for sensor in sensors {
-        x = sensor.pos.x - sensor.distance;
-        y = sensor.pos.y - 1;
-        while x < sensor.pos.x {
-            // Left-Upper side
-            x += 1;
-            y -= 1;
-            if x < 0 || x > max_val || y < 0 || y > max_val {
-                continue;
-            }
-            if !sensors.iter().any(|sensor| {
-                manhattan_distance(sensor.pos.x, sensor.pos.y, x, y) <= sensor.distance
-            }) {
-                break 'outer;
-            }
-        }
-        while y < sensor.pos.y {
-            // Right-Upper side
-            x += 1;
-            y += 1;
-            if x < 0 || x > max_val || y < 0 || y > max_val {
-                continue;
-            }
-            if !sensors.iter().any(|sensor| {
-                manhattan_distance(sensor.pos.x, sensor.pos.y, x, y) <= sensor.distance
-            }) {
-                break 'outer;
-            }
-        }
-        while x > sensor.pos.x {
-            // Right-Lower side
-            x -= 1;
-            y += 1;
-            if x < 0 || x > max_val || y < 0 || y > max_val {
-                continue;
-            }
-            if !sensors.iter().any(|sensor| {
-                manhattan_distance(sensor.pos.x, sensor.pos.y, x, y) <= sensor.distance
-            }) {
-                break 'outer;
-            }
-        }
-        while y > sensor.pos.y {
-            // Left-Lower side
-            x -= 1;
-            y -= 1;
-            if x < 0 || x > max_val || y < 0 || y > max_val {
-                continue;
-            }
-            if !sensors.iter().any(|sensor| {
-                manhattan_distance(sensor.pos.x, sensor.pos.y, x, y) <= sensor.distance
-            }) {
-                break 'outer;
-            }
-        }
-    }
-    x * 4_000_000 + y
+    let areas: Vec<Diamond> = sensors.iter().map(Sensor::area).collect();
+    let beacon = geom::find_uncovered(
+        &areas,
+        Point2D { x: 0, y: 0 },
+        Point2D {
+            x: max_val,
+            y: max_val,
+        },
+    )
+    .expect("No uncovered position found!");
+    beacon.x * 4_000_000 + beacon.y
 }
 
 fn main() {
     let sensors = utils::load_puzzle_data(15, parser);
     let spaces_not_beacon_row_2000000 = solve(sensors.as_slice(), 2000000);
     println!("Solution 1: There are {spaces_not_beacon_row_2000000} that are not beacons on row 2000000.");
+    if std::env::args().any(|arg| arg == "--gaps") {
+        let gaps = row_gaps(sensors.as_slice(), 2000000, 0..=4_000_000);
+        println!("Uncovered on row 2000000: {gaps:?}");
+    }
 
     let tuning_freq = solve2(sensors.as_slice(), 4_000_000);
     println!("Solution 2: The tuning frequency of the beacon is: {tuning_freq}");
@@ -154,7 +117,7 @@ fn main() {
 
 #[cfg(test)]
 mod tests {
-    use crate::{parser, solve, solve2};
+    use crate::{parser, row_coverage, row_gaps, solve, solve2};
 
     #[test]
     fn test_puzzle() {
@@ -163,6 +126,18 @@ mod tests {
         assert_eq!(solution, 26);
     }
 
+    #[test]
+    fn test_rows() {
+        let test_data = utils::load_puzzle_test(15, parser);
+        assert_eq!(row_coverage(&test_data, 10), vec![-2..=24]);
+        assert_eq!(row_gaps(&test_data, 10, 0..=20), vec![]);
+        assert_eq!(row_gaps(&test_data, 10, -5..=30), vec![-5..=-3, 25..=30]);
+        // Where the distress beacon is
+        assert_eq!(row_gaps(&test_data, 11, 0..=20), vec![14..=14]);
+        // The sensor at 0,11 counts, as a beacon can't be there either
+        assert_eq!(solve(test_data.as_slice(), 11), 28);
+    }
+
     #[test]
     fn test_puzzle2() {
         let test_data = utils::load_puzzle_test(15, parser);
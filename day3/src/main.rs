@@ -1,14 +1,17 @@
 #![feature(iter_array_chunks)]
 
+use utils::bitset::BitSet64;
+
+/// Items are stored as a set of priorities
 struct Sack {
-    first: Vec<char>,
-    second: Vec<char>,
+    first: BitSet64,
+    second: BitSet64,
 }
 
 struct Group {
-    first: Vec<char>,
-    second: Vec<char>,
-    third: Vec<char>,
+    first: BitSet64,
+    second: BitSet64,
+    third: BitSet64,
 }
 
 fn parser(s: String) -> Vec<Sack> {
@@ -16,8 +19,8 @@ fn parser(s: String) -> Vec<Sack> {
         .map(|g| {
             let (first, second) = g.split_at(g.len() / 2);
             Sack {
-                first: first.chars().collect(),
-                second: second.chars().collect(),
+                first: BitSet64::from_letters(first),
+                second: BitSet64::from_letters(second),
             }
         })
         .collect()
@@ -27,9 +30,9 @@ fn parser2(s: String) -> Vec<Group> {
     s.split('\n')
         .array_chunks::<3>()
         .map(|g| Group {
-            first: g.first().unwrap().chars().collect(),
-            second: g.get(1).unwrap().chars().collect(),
-            third: g.get(2).unwrap().chars().collect(),
+            first: BitSet64::from_letters(g.first().unwrap()),
+            second: BitSet64::from_letters(g.get(1).unwrap()),
+            third: BitSet64::from_letters(g.get(2).unwrap()),
         })
         .collect()
 }
@@ -37,49 +40,19 @@ fn parser2(s: String) -> Vec<Group> {
 fn solve(sacks: Vec<Sack>) -> u32 {
     // a-z = 1-26
     // A-Z = 27-52
-    let matching_items: Vec<u32> = sacks
+    sacks
         .iter()
-        .map(|sack| {
-            let shared = sack
-                .first
-                .iter()
-                .find(|&fc| sack.second.contains(fc))
-                .unwrap();
-            // priority
-            match shared.is_ascii_lowercase() {
-                true => *shared as u32 - 96,
-                false => *shared as u32 - 64 + 26,
-            }
-        })
-        .collect();
-    matching_items.iter().sum()
+        .map(|sack| (sack.first & sack.second).first().unwrap() as u32)
+        .sum()
 }
 
 fn solve2(groups: Vec<Group>) -> u32 {
     // a-z = 1-26
     // A-Z = 27-52
-    let matching_items: Vec<u32> = groups
+    groups
         .iter()
-        .map(|group| {
-            let mut shared_first_second: Vec<&char> = group
-                .first
-                .iter()
-                .filter(|&fc| group.second.contains(fc))
-                .collect();
-            shared_first_second.sort();
-            shared_first_second.dedup();
-            let shared_third = shared_first_second
-                .into_iter()
-                .find(|&&fc| group.third.contains(&fc))
-                .unwrap();
-            // priority
-            match shared_third.is_ascii_lowercase() {
-                true => *shared_third as u32 - 96,
-                false => *shared_third as u32 - 64 + 26,
-            }
-        })
-        .collect();
-    matching_items.iter().sum()
+        .map(|group| (group.first & group.second & group.third).first().unwrap() as u32)
+        .sum()
 }
 
 fn main() {
@@ -1,4 +1,4 @@
-#![feature(iter_array_chunks)]
+use std::{error::Error, fmt};
 
 use regex::Regex;
 use utils::SliceExt;
@@ -9,38 +9,215 @@ struct MovementInstruction {
     to_ndx: usize,
 }
 
-struct PuzzleInput {
-    stacks: Vec<Vec<char>>,
-    instructions: Vec<MovementInstruction>,
+impl fmt::Display for MovementInstruction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "move {} from {} to {}", self.count, self.from_ndx + 1, self.to_ndx + 1)
+    }
 }
 
-fn parser(s: String) -> PuzzleInput {
-    let (cargo_map, instructions) = s.split_once("\n\n").unwrap();
-    let lines: Vec<Vec<char>> = cargo_map
-        .split_inclusive('\n')
-        .map(|line| {
-            line.chars()
-                .array_chunks::<4>()
-                .map(|chunk| *chunk.get(1).unwrap())
-                .collect()
+/// A crate's label, usually a single letter
+type Crate = String;
+
+/// How a crane moves crates between stacks
+trait CraneModel {
+    /// Moves the top `count` crates (which are known to be there) from `from` onto `to`
+    fn move_crates(&self, from: &mut Vec<Crate>, to: &mut Vec<Crate>, count: usize);
+}
+
+/// Moves crates one at a time, so they end up reversed
+struct CrateMover9000;
+
+impl CraneModel for CrateMover9000 {
+    fn move_crates(&self, from: &mut Vec<Crate>, to: &mut Vec<Crate>, count: usize) {
+        to.extend(from.drain((from.len() - count)..).rev());
+    }
+}
+
+/// Moves crates all at once, keeping their order
+struct CrateMover9001;
+
+impl CraneModel for CrateMover9001 {
+    fn move_crates(&self, from: &mut Vec<Crate>, to: &mut Vec<Crate>, count: usize) {
+        to.extend(from.drain((from.len() - count)..));
+    }
+}
+
+/// A crane that can lift at most `capacity` crates at once, so bigger moves take several trips
+struct Limited<M> {
+    model: M,
+    capacity: usize,
+}
+
+impl<M: CraneModel> Limited<M> {
+    fn new(model: M, capacity: usize) -> Self {
+        assert!(capacity > 0, "A crane has to lift something");
+        Limited { model, capacity }
+    }
+}
+
+impl<M: CraneModel> CraneModel for Limited<M> {
+    fn move_crates(&self, from: &mut Vec<Crate>, to: &mut Vec<Crate>, mut count: usize) {
+        while count > 0 {
+            let trip = count.min(self.capacity);
+            self.model.move_crates(from, to, trip);
+            count -= trip;
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum MoveErrorKind {
+    /// Stack numbers start at 1, like in the instructions
+    BadStack(usize),
+    Underflow { stack: usize, requested: usize, available: usize },
+}
+
+#[derive(Debug, PartialEq, Eq)]
+struct MoveError {
+    /// Index of the failed instruction
+    instruction: usize,
+    kind: MoveErrorKind,
+}
+
+impl fmt::Display for MoveError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "instruction {}: ", self.instruction)?;
+        match self.kind {
+            MoveErrorKind::BadStack(stack) => write!(f, "there's no stack {stack}"),
+            MoveErrorKind::Underflow { stack, requested, available } => {
+                write!(f, "can't move {requested} crate(s) from stack {stack}, it only has {available}")
+            }
+        }
+    }
+}
+
+impl Error for MoveError {}
+
+/// Stacks of crates, bottom first
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Stacks(Vec<Vec<Crate>>);
+
+impl Stacks {
+    /// Reads the puzzle's drawing, including the numbered footer. Crates are drawn as
+    /// "[label]", all the same width, with one space between stacks.
+    fn parse(drawing: &str) -> Stacks {
+        let mut lines: Vec<&str> = drawing.lines().collect();
+        let footer = lines.pop().expect("Drawing needs a footer");
+        let mut stacks = vec![vec![]; footer.split_whitespace().count()];
+        for line in lines.into_iter().rev() {
+            let mut offset = 0;
+            while let Some(start) = line[offset..].find('[').map(|s| s + offset) {
+                let end = start + line[start..].find(']').expect("Crate isn't closed");
+                // Each stack takes the crate's width plus a space
+                let ndx = start / (end - start + 2);
+                if ndx >= stacks.len() {
+                    stacks.resize(ndx + 1, vec![]);
+                }
+                stacks[ndx].push(line[start + 1..end].trim().to_string());
+                offset = end + 1;
+            }
+        }
+        Stacks(stacks)
+    }
+
+    /// The drawing after each instruction, as "move ..." followed by the stacks. Stops at the
+    /// first move that fails.
+    fn animate<'a>(
+        mut self,
+        instructions: &'a [MovementInstruction],
+        crane: &'a dyn CraneModel,
+    ) -> impl Iterator<Item = Result<String, MoveError>> + 'a {
+        let mut failed = false;
+        instructions.iter().enumerate().map_while(move |(ndx, instruction)| {
+            if failed {
+                return None;
+            }
+            Some(match self.apply(instruction, crane) {
+                Ok(()) => Ok(format!("{instruction}\n{self}")),
+                Err(kind) => {
+                    failed = true;
+                    Err(MoveError { instruction: ndx, kind })
+                }
+            })
         })
-        .collect();
-    // [[' ', 'D', ' '],
-    //  ['N', 'C', ' '],
-    //  ['Z', 'M', 'P'],
-    let mut stacks = vec![vec![]; lines[0].len()];
-    for line in lines {
-        for (ndx, item) in line.into_iter().enumerate() {
-            if item.is_ascii_uppercase() {
-                stacks[ndx].insert(0, item);
+    }
+
+    fn apply(&mut self, instruction: &MovementInstruction, crane: &dyn CraneModel) -> Result<(), MoveErrorKind> {
+        for ndx in [instruction.from_ndx, instruction.to_ndx] {
+            if ndx >= self.0.len() {
+                return Err(MoveErrorKind::BadStack(ndx + 1));
             }
         }
+        let available = self.0[instruction.from_ndx].len();
+        if instruction.count > available {
+            return Err(MoveErrorKind::Underflow {
+                stack: instruction.from_ndx + 1,
+                requested: instruction.count,
+                available,
+            });
+        }
+        if instruction.from_ndx == instruction.to_ndx {
+            // Picked up and put back down
+            let mut lifted = vec![];
+            let stack = &mut self.0[instruction.from_ndx];
+            crane.move_crates(stack, &mut lifted, instruction.count);
+            crane.move_crates(&mut lifted, stack, instruction.count);
+            return Ok(());
+        }
+        let (from, to) = self.0.get_two_mut(instruction.from_ndx, instruction.to_ndx);
+        crane.move_crates(from, to, instruction.count);
+        Ok(())
+    }
+
+    fn run(&mut self, instructions: &[MovementInstruction], crane: &dyn CraneModel) -> Result<(), MoveError> {
+        for (ndx, instruction) in instructions.iter().enumerate() {
+            self.apply(instruction, crane)
+                .map_err(|kind| MoveError { instruction: ndx, kind })?;
+        }
+        Ok(())
     }
-    // [['N', 'Z'], ['D', 'C', 'M'], ['P']]
+
+    /// The crate on top of each stack (empty stacks are skipped)
+    fn tops(&self) -> String {
+        self.0.iter().filter_map(|stack| stack.last().map(String::as_str)).collect()
+    }
+}
+
+/// Draws the stacks like the puzzle, with the numbered footer
+impl fmt::Display for Stacks {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let label_width = self.0.iter().flatten().map(|c| c.chars().count()).max().unwrap_or(1);
+        // Wide enough for the crates and the stack numbers
+        let width = (label_width + 2).max(self.0.len().to_string().len());
+        let label_width = width - 2;
+        let height = self.0.iter().map(Vec::len).max().unwrap_or(0);
+        for level in (0..height).rev() {
+            let cells: Vec<String> = self
+                .0
+                .iter()
+                .map(|stack| match stack.get(level) {
+                    Some(label) => format!("[{label:^label_width$}]"),
+                    None => " ".repeat(width),
+                })
+                .collect();
+            writeln!(f, "{}", cells.join(" "))?;
+        }
+        let numbers: Vec<String> = (1..=self.0.len()).map(|n| format!("{n:^width$}")).collect();
+        write!(f, "{}", numbers.join(" "))
+    }
+}
+
+struct PuzzleInput {
+    stacks: Stacks,
+    instructions: Vec<MovementInstruction>,
+}
+
+fn parser(s: String) -> PuzzleInput {
+    let (cargo_map, instructions) = s.split_once("\n\n").unwrap();
     let re = Regex::new(r"move (\d+) from (\d+) to (\d+)").unwrap();
     // Now instructions...
     PuzzleInput {
-        stacks,
+        stacks: Stacks::parse(cargo_map),
         instructions: instructions
             .split('\n')
             .map(|s| {
@@ -55,35 +232,31 @@ fn parser(s: String) -> PuzzleInput {
     }
 }
 
-fn solve(mut cargo: PuzzleInput) -> String {
-    for instruction in cargo.instructions {
-        let (from, to) = cargo
-            .stacks
-            .get_two_mut(instruction.from_ndx, instruction.to_ndx);
-        to.extend(from.drain((from.len() - instruction.count)..).rev());
-    }
+fn solve_with(mut cargo: PuzzleInput, crane: &dyn CraneModel) -> String {
     cargo
         .stacks
-        .into_iter()
-        .map(|vc| vc.last().unwrap().to_owned())
-        .collect()
+        .run(&cargo.instructions, crane)
+        .unwrap_or_else(|e| panic!("The crane got stuck: {e}"));
+    cargo.stacks.tops()
 }
 
-fn solve2(mut cargo: PuzzleInput) -> String {
-    for instruction in cargo.instructions {
-        let (from, to) = cargo
-            .stacks
-            .get_two_mut(instruction.from_ndx, instruction.to_ndx);
-        to.extend(from.drain((from.len() - instruction.count)..));
-    }
-    cargo
-        .stacks
-        .into_iter()
-        .map(|vc| vc.last().unwrap().to_owned())
-        .collect()
+fn solve(cargo: PuzzleInput) -> String {
+    solve_with(cargo, &CrateMover9000)
+}
+
+fn solve2(cargo: PuzzleInput) -> String {
+    solve_with(cargo, &CrateMover9001)
 }
 
 fn main() {
+    // `--animate` draws the stacks after every move of the CrateMover 9001
+    if std::env::args().any(|arg| arg == "--animate") {
+        let cargo = utils::load_puzzle_data(5, parser);
+        for frame in cargo.stacks.animate(&cargo.instructions, &CrateMover9001) {
+            println!("{}\n", frame.unwrap_or_else(|e| panic!("The crane got stuck: {e}")));
+        }
+    }
+
     let cargo = utils::load_puzzle_data(5, parser);
     let top_of_cargo = solve(cargo);
     println!("Solution 1: Items on top of the stacks: {top_of_cargo}",);
@@ -91,11 +264,32 @@ fn main() {
     let cargo = utils::load_puzzle_data(5, parser);
     let top_of_cargo = solve2(cargo);
     println!("Solution 2: Items on top of the stacks: {top_of_cargo}",);
+
+    // `--capacity N` runs the CrateMover 9001 again, lifting at most N crates per trip
+    let args: Vec<String> = std::env::args().collect();
+    if let Some(ndx) = args.iter().position(|arg| arg == "--capacity") {
+        let capacity: usize = args
+            .get(ndx + 1)
+            .and_then(|n| n.parse().ok())
+            .filter(|&n| n > 0)
+            .expect("--capacity needs a number above 0");
+        let cargo = utils::load_puzzle_data(5, parser);
+        let top_of_cargo = solve_with(cargo, &Limited::new(CrateMover9001, capacity));
+        println!("Lifting {capacity} at a time: Items on top of the stacks: {top_of_cargo}");
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::{parser, solve, solve2};
+    use crate::{
+        parser, solve, solve2, solve_with, CrateMover9000, CrateMover9001, Limited, MovementInstruction,
+        MoveError, MoveErrorKind, Stacks,
+    };
+
+    /// One stack per string, one single letter crate per char (bottom first)
+    fn letters(stacks: &[&str]) -> Stacks {
+        Stacks(stacks.iter().map(|s| s.chars().map(String::from).collect()).collect())
+    }
 
     #[test]
     fn test_puzzle() {
@@ -109,4 +303,83 @@ mod tests {
         let solution = solve2(test_data);
         assert_eq!(solution, "MCD".to_string());
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_cranes() {
+        // Moving 3 crates 2 at a time puts N D on P, then Z on top
+        let test_data = utils::load_puzzle_test(5, parser);
+        assert_eq!(solve_with(test_data, &Limited::new(CrateMover9001, 2)), "MCZ".to_string());
+        let test_data = utils::load_puzzle_test(5, parser);
+        assert_eq!(solve_with(test_data, &Limited::new(CrateMover9000, 1)), "CMZ".to_string());
+
+        let mut stacks = letters(&["ABC", ""]);
+        let mut limited = stacks.clone();
+        let move_all = MovementInstruction { count: 3, from_ndx: 0, to_ndx: 1 };
+        stacks.apply(&move_all, &CrateMover9001).unwrap();
+        assert_eq!(stacks, letters(&["", "ABC"]));
+        limited.apply(&move_all, &Limited::new(CrateMover9001, 2)).unwrap();
+        assert_eq!(limited, letters(&["", "BCA"]));
+
+        let in_place = MovementInstruction { count: 2, from_ndx: 1, to_ndx: 1 };
+        stacks.apply(&in_place, &CrateMover9000).unwrap();
+        assert_eq!(stacks.tops(), "C");
+    }
+
+    #[test]
+    fn test_move_errors() {
+        let mut stacks = letters(&["A", "B"]);
+        let instructions = [
+            MovementInstruction { count: 1, from_ndx: 0, to_ndx: 1 },
+            MovementInstruction { count: 3, from_ndx: 1, to_ndx: 0 },
+        ];
+        assert_eq!(
+            stacks.run(&instructions, &CrateMover9000),
+            Err(MoveError {
+                instruction: 1,
+                kind: MoveErrorKind::Underflow { stack: 2, requested: 3, available: 2 }
+            })
+        );
+        let bad_stack = MovementInstruction { count: 1, from_ndx: 0, to_ndx: 9 };
+        assert_eq!(stacks.apply(&bad_stack, &CrateMover9000), Err(MoveErrorKind::BadStack(10)));
+    }
+
+    #[test]
+    fn test_render() {
+        let drawing = "    [D]    \n[N] [C]    \n[Z] [M] [P]\n 1   2   3 ";
+        let mut cargo = Stacks::parse(drawing);
+        assert_eq!(cargo, letters(&["ZN", "MCD", "P"]));
+        assert_eq!(cargo.to_string(), drawing);
+
+        // Wider labels, and more stacks than single digits
+        cargo.0[0].push("AB".to_string());
+        cargo.0.extend(std::iter::repeat_n(vec![], 7));
+        cargo.0[9].push("Q".to_string());
+        let drawing = cargo.to_string();
+        assert_eq!(
+            drawing.lines().last().unwrap(),
+            " 1    2    3    4    5    6    7    8    9    10 "
+        );
+        let lines: Vec<&str> = drawing.lines().collect();
+        assert_eq!(lines[0], format!("[AB] [D ]{}", " ".repeat(40)));
+        assert_eq!(lines[2], format!("[Z ] [M ] [P ]{}[Q ]", " ".repeat(31)));
+        assert_eq!(Stacks::parse(&drawing), cargo);
+    }
+
+    #[test]
+    fn test_animate() {
+        let test_data = utils::load_puzzle_test(5, parser);
+        let frames: Vec<String> = test_data
+            .stacks
+            .animate(&test_data.instructions, &CrateMover9000)
+            .map(Result::unwrap)
+            .collect();
+        assert_eq!(frames.len(), 4);
+        assert_eq!(frames[0], "move 1 from 2 to 1\n[D]        \n[N] [C]    \n[Z] [M] [P]\n 1   2   3 ");
+        assert_eq!(frames[3], "move 1 from 1 to 2\n        [Z]\n        [N]\n        [D]\n[C] [M] [P]\n 1   2   3 ");
+
+        let bad = [MovementInstruction { count: 9, from_ndx: 0, to_ndx: 1 }];
+        let mut frames = letters(&["A", "B"]).animate(&bad, &CrateMover9000);
+        assert!(matches!(frames.next(), Some(Err(MoveError { instruction: 0, .. }))));
+        assert_eq!(frames.next(), None);
+    }
+}
@@ -1,43 +1,126 @@
-#![feature(iter_array_chunks)]
+use std::io::{self, BufReader, Read};
+
+/// Finds markers (a window of all different bytes) one byte at a time
+struct MarkerDetector {
+    window: usize,
+    /// How many times each byte is in the window
+    counts: [usize; 256],
+    /// Bytes that are in the window at least once
+    distinct: usize,
+    /// The last `window` bytes, indexed by received % window
+    recent: Vec<u8>,
+    received: usize,
+}
+
+impl MarkerDetector {
+    fn new(window: usize) -> Self {
+        assert!(window > 0, "Markers must be at least 1 byte long");
+        MarkerDetector {
+            window,
+            counts: [0; 256],
+            distinct: 0,
+            recent: vec![0; window],
+            received: 0,
+        }
+    }
+
+    /// Returns the number of bytes received if they end with a marker
+    fn push(&mut self, byte: u8) -> Option<usize> {
+        let slot = self.received % self.window;
+        if self.received >= self.window {
+            // Oldest byte leaves the window
+            let old = self.recent[slot] as usize;
+            self.counts[old] -= 1;
+            if self.counts[old] == 0 {
+                self.distinct -= 1;
+            }
+        }
+        self.recent[slot] = byte;
+        self.counts[byte as usize] += 1;
+        if self.counts[byte as usize] == 1 {
+            self.distinct += 1;
+        }
+        self.received += 1;
+        (self.distinct == self.window).then_some(self.received)
+    }
+}
+
+/// Every marker's position (bytes received when it's complete), reading bytes as they come.
+/// Stops after the first error.
+fn markers<I, E>(bytes: I, window: usize) -> impl Iterator<Item = Result<usize, E>>
+where
+    I: IntoIterator<Item = Result<u8, E>>,
+{
+    let mut detector = MarkerDetector::new(window);
+    let mut failed = false;
+    bytes
+        .into_iter()
+        .map_while(move |byte| match byte {
+            _ if failed => None,
+            Ok(byte) => Some(detector.push(byte).map(Ok)),
+            Err(e) => {
+                failed = true;
+                Some(Some(Err(e)))
+            }
+        })
+        .flatten()
+}
 
 fn parser(s: String) -> String {
     // Nothing to parse
     s
 }
 
-fn solve(data_stream: String, marker_len: usize) -> usize {
-    data_stream
-        .as_bytes()
-        .windows(marker_len)
-        .position(|s| !(1..s.len()).any(|n| s[n..].contains(&s[n - 1])))
-        .unwrap() + marker_len
+/// Bytes received before the first marker is complete, None if there isn't one
+fn solve<R: Read>(data_stream: R, marker_len: usize) -> io::Result<Option<usize>> {
+    markers(BufReader::new(data_stream).bytes(), marker_len).next().transpose()
 }
 
 fn main() {
     let data = utils::load_puzzle_data(6, parser);
-    let marker_ndx = solve(data, 4);
+    let marker_ndx = solve(data.as_bytes(), 4).unwrap().expect("No packet marker");
     println!("Solution 1: First packet marker comes after {marker_ndx} chars received.",);
 
     let data = utils::load_puzzle_data(6, parser);
-    let marker_ndx = solve(data, 14);
+    let marker_ndx = solve(data.as_bytes(), 14).unwrap().expect("No message marker");
     println!("Solution 2: First message marker comes after {marker_ndx} chars received.",);
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::{parser, solve};
+    use std::convert::Infallible;
+
+    use crate::{markers, parser, solve};
 
     #[test]
     fn test_puzzle() {
         let test_data = utils::load_puzzle_test(6, parser);
-        let solution = solve(test_data, 4);
-        assert_eq!(solution, 10);
+        let solution = solve(test_data.as_bytes(), 4).unwrap();
+        assert_eq!(solution, Some(10));
     }
 
     #[test]
     fn test_puzzle2() {
         let test_data = utils::load_puzzle_test(6, parser);
-        let solution = solve(test_data, 14);
-        assert_eq!(solution, 29);
+        let solution = solve(test_data.as_bytes(), 14).unwrap();
+        assert_eq!(solution, Some(29));
+    }
+
+    #[test]
+    fn test_markers() {
+        let stream = "mjqjpqmgbljsphdztnvjfqwrcgsmlb";
+        let found: Vec<usize> = markers(stream.bytes().map(Ok::<_, Infallible>), 4)
+            .take(3)
+            .map(Result::unwrap)
+            .collect();
+        assert_eq!(found, vec![7, 8, 9]);
+        assert_eq!(solve(stream.as_bytes(), 14).unwrap(), Some(19));
+        assert_eq!(solve("aabbaabb".as_bytes(), 2).unwrap(), Some(3));
+        assert_eq!(solve("aaaa".as_bytes(), 2).unwrap(), None);
+        assert_eq!(solve("abc".as_bytes(), 4).unwrap(), None);
+
+        // Nothing after an error
+        let bytes = [Ok(b'a'), Ok(b'b'), Err("oops"), Ok(b'c')];
+        assert_eq!(markers(bytes, 2).collect::<Vec<_>>(), vec![Ok(2), Err("oops")]);
     }
 }
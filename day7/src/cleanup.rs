@@ -0,0 +1,264 @@
+use std::{error::Error, fmt};
+
+use crate::fs::{FileSystem, NodeId, ROOT};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DiskPolicy {
+    pub capacity: usize,
+    /// Free space needed (e.g. for the update)
+    pub required_free: usize,
+    /// Directories at most this big count as small
+    pub threshold: usize,
+}
+
+impl Default for DiskPolicy {
+    /// The puzzle's device
+    fn default() -> Self {
+        DiskPolicy {
+            capacity: 70000000,
+            required_free: 30000000,
+            threshold: 100000,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum CleanupError {
+    UsedExceedsCapacity {
+        used: usize,
+        capacity: usize,
+    },
+    /// Even deleting everything wouldn't free enough
+    CannotFree {
+        required: usize,
+        capacity: usize,
+    },
+}
+
+impl fmt::Display for CleanupError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CleanupError::UsedExceedsCapacity { used, capacity } => {
+                write!(f, "{used} bytes used on a {capacity} byte disk")
+            }
+            CleanupError::CannotFree { required, capacity } => {
+                write!(f, "can't free {required} bytes on a {capacity} byte disk")
+            }
+        }
+    }
+}
+
+impl Error for CleanupError {}
+
+/// Decides what to delete from a filesystem under a DiskPolicy
+pub struct CleanupPlanner<'a> {
+    fs: &'a FileSystem,
+    policy: DiskPolicy,
+    /// Indexed by NodeId
+    sizes: Vec<usize>,
+}
+
+impl<'a> CleanupPlanner<'a> {
+    pub fn new(fs: &'a FileSystem, policy: DiskPolicy) -> Self {
+        CleanupPlanner {
+            fs,
+            policy,
+            sizes: fs.sizes(),
+        }
+    }
+
+    fn dir_ids(&self) -> impl Iterator<Item = NodeId> + '_ {
+        (0..self.fs.len()).filter(|id| self.fs.node(*id).is_dir())
+    }
+
+    fn entry(&self, id: NodeId) -> (String, usize) {
+        (self.fs.path(id), self.sizes[id])
+    }
+
+    /// Directories no bigger than the threshold, as (path, size)
+    pub fn small_dirs(&self) -> Vec<(String, usize)> {
+        self.dir_ids()
+            .filter(|id| self.sizes[*id] <= self.policy.threshold)
+            .map(|id| self.entry(id))
+            .collect()
+    }
+
+    /// Total of the small directories' sizes (nested ones count more than once)
+    pub fn small_dirs_total(&self) -> usize {
+        self.small_dirs().iter().map(|(_, size)| size).sum()
+    }
+
+    /// How much more space has to be freed
+    pub fn space_needed(&self) -> Result<usize, CleanupError> {
+        let DiskPolicy {
+            capacity,
+            required_free,
+            ..
+        } = self.policy;
+        let used = self.sizes[ROOT];
+        if used > capacity {
+            return Err(CleanupError::UsedExceedsCapacity { used, capacity });
+        }
+        if required_free > capacity {
+            return Err(CleanupError::CannotFree {
+                required: required_free,
+                capacity,
+            });
+        }
+        Ok(required_free.saturating_sub(capacity - used))
+    }
+
+    /// The smallest single directory that frees enough, as (path, size)
+    pub fn smallest_sufficient(&self) -> Result<(String, usize), CleanupError> {
+        let needed = self.space_needed()?;
+        // The root always frees enough, as required_free fits on the disk
+        let id = self
+            .dir_ids()
+            .filter(|id| self.sizes[*id] >= needed)
+            .min_by_key(|id| self.sizes[*id])
+            .unwrap_or(ROOT);
+        Ok(self.entry(id))
+    }
+
+    fn overlaps(&self, a: NodeId, b: NodeId) -> bool {
+        self.is_ancestor(a, b) || self.is_ancestor(b, a)
+    }
+
+    // Whether `a` is `b` or contains it
+    fn is_ancestor(&self, a: NodeId, mut b: NodeId) -> bool {
+        loop {
+            if a == b {
+                return true;
+            }
+            if b == ROOT {
+                return false;
+            }
+            b = self.fs.node(b).parent;
+        }
+    }
+
+    /// The fewest directories (other than the root, unless nothing else will do) that free
+    /// enough between them, then each swapped for the smallest directory that still frees
+    /// enough. Empty if there's already enough space.
+    pub fn minimal_set(&self) -> Result<Vec<(String, usize)>, CleanupError> {
+        let needed = self.space_needed()?;
+        if needed == 0 {
+            return Ok(vec![]);
+        }
+
+        // A directory is never smaller than one inside it, so the biggest top level
+        // directories free the most for how many are deleted
+        let mut top: Vec<NodeId> = self
+            .fs
+            .children(ROOT)
+            .map(|(_, id)| id)
+            .filter(|id| self.fs.node(*id).is_dir())
+            .collect();
+        top.sort_by_key(|id| std::cmp::Reverse(self.sizes[*id]));
+        let mut chosen = vec![];
+        let mut freed = 0;
+        for id in top {
+            if freed >= needed {
+                break;
+            }
+            chosen.push(id);
+            freed += self.sizes[id];
+        }
+        if freed < needed {
+            // Files in the root are in the way
+            return Ok(vec![self.entry(ROOT)]);
+        }
+
+        // Shrink the choices, smallest first
+        chosen.reverse();
+        for ndx in 0..chosen.len() {
+            let others: usize = chosen
+                .iter()
+                .enumerate()
+                .filter(|(n, _)| *n != ndx)
+                .map(|(_, id)| self.sizes[*id])
+                .sum();
+            let best = self
+                .dir_ids()
+                .filter(|id| *id != ROOT && self.sizes[*id] + others >= needed)
+                .filter(|id| {
+                    chosen
+                        .iter()
+                        .enumerate()
+                        .all(|(n, other)| n == ndx || !self.overlaps(*id, *other))
+                })
+                .min_by_key(|id| self.sizes[*id]);
+            if let Some(best) = best {
+                chosen[ndx] = best;
+            }
+        }
+        chosen.sort_by_key(|id| std::cmp::Reverse(self.sizes[*id]));
+        Ok(chosen.into_iter().map(|id| self.entry(id)).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::fs::{FileSystem, ROOT};
+
+    use super::{CleanupError, CleanupPlanner, DiskPolicy};
+
+    fn example() -> FileSystem {
+        // /a (100, with /a/x 60), /b (50), /c (30), /f (10)
+        let mut fs = FileSystem::new();
+        let a = fs.add_dir(ROOT, "a").unwrap();
+        let x = fs.add_dir(a, "x").unwrap();
+        fs.add_file(x, "1", 60).unwrap();
+        fs.add_file(a, "2", 40).unwrap();
+        let b = fs.add_dir(ROOT, "b").unwrap();
+        fs.add_file(b, "3", 50).unwrap();
+        let c = fs.add_dir(ROOT, "c").unwrap();
+        fs.add_file(c, "4", 30).unwrap();
+        fs.add_file(ROOT, "f", 10).unwrap();
+        fs
+    }
+
+    fn policy(capacity: usize, required_free: usize) -> DiskPolicy {
+        DiskPolicy {
+            capacity,
+            required_free,
+            threshold: 50,
+        }
+    }
+
+    #[test]
+    fn test_planner() {
+        let fs = example();
+        let planner = CleanupPlanner::new(&fs, policy(200, 50));
+        assert_eq!(planner.space_needed(), Ok(40));
+        assert_eq!(planner.small_dirs_total(), 50 + 30);
+        assert_eq!(planner.smallest_sufficient(), Ok(("/b".to_string(), 50)));
+        assert_eq!(planner.minimal_set(), Ok(vec![("/b".to_string(), 50)]));
+
+        // Needs two directories: /a alone isn't enough, /a and /c is the smallest pair
+        let planner = CleanupPlanner::new(&fs, policy(200, 125));
+        assert_eq!(planner.space_needed(), Ok(115));
+        assert_eq!(
+            planner.minimal_set(),
+            Ok(vec![("/a".to_string(), 100), ("/c".to_string(), 30)])
+        );
+        assert_eq!(planner.smallest_sufficient(), Ok(("/".to_string(), 190)));
+
+        // Only deleting everything does it
+        let planner = CleanupPlanner::new(&fs, policy(200, 195));
+        assert_eq!(planner.minimal_set(), Ok(vec![("/".to_string(), 190)]));
+
+        let planner = CleanupPlanner::new(&fs, policy(200, 10));
+        assert_eq!(planner.minimal_set(), Ok(vec![]));
+        assert!(CleanupPlanner::new(&fs, policy(100, 10))
+            .space_needed()
+            .is_err());
+        assert_eq!(
+            CleanupPlanner::new(&fs, policy(200, 300)).minimal_set(),
+            Err(CleanupError::CannotFree {
+                required: 300,
+                capacity: 200
+            })
+        );
+    }
+}
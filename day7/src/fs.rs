@@ -0,0 +1,478 @@
+use std::{collections::BTreeMap, error::Error, fmt, fmt::Write};
+
+/// Index into the FileSystem's nodes
+pub type NodeId = usize;
+
+pub const ROOT: NodeId = 0;
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum NodeKind {
+    Dir {
+        children: BTreeMap<String, NodeId>,
+        /// Whether `ls` has been run in it
+        listed: bool,
+    },
+    File {
+        size: usize,
+    },
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct Node {
+    pub name: String,
+    /// The root is its own parent, like `cd ..` in /
+    pub parent: NodeId,
+    pub kind: NodeKind,
+}
+
+impl Node {
+    pub fn is_dir(&self) -> bool {
+        matches!(self.kind, NodeKind::Dir { .. })
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum FsError {
+    NotFound(String),
+    NotADirectory(String),
+    /// Something different already exists at the path
+    Conflict(String),
+}
+
+impl fmt::Display for FsError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            FsError::NotFound(path) => write!(f, "'{path}' not found"),
+            FsError::NotADirectory(path) => write!(f, "'{path}' is not a directory"),
+            FsError::Conflict(path) => write!(f, "'{path}' conflicts with what's already there"),
+        }
+    }
+}
+
+impl Error for FsError {}
+
+/// A directory tree, with every node stored in one Vec. Parents always come before their
+/// children.
+#[derive(Debug)]
+pub struct FileSystem {
+    nodes: Vec<Node>,
+}
+
+impl Default for FileSystem {
+    fn default() -> Self {
+        FileSystem {
+            nodes: vec![Node {
+                name: String::new(),
+                parent: ROOT,
+                kind: NodeKind::Dir {
+                    children: BTreeMap::new(),
+                    listed: false,
+                },
+            }],
+        }
+    }
+}
+
+impl FileSystem {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn node(&self, id: NodeId) -> &Node {
+        &self.nodes[id]
+    }
+
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// The directory's entries, sorted by name (empty for files)
+    pub fn children(&self, id: NodeId) -> impl Iterator<Item = (&str, NodeId)> {
+        let children = match &self.nodes[id].kind {
+            NodeKind::Dir { children, .. } => Some(children),
+            NodeKind::File { .. } => None,
+        };
+        children
+            .into_iter()
+            .flatten()
+            .map(|(name, id)| (name.as_str(), *id))
+    }
+
+    pub fn child(&self, dir: NodeId, name: &str) -> Option<NodeId> {
+        match &self.nodes[dir].kind {
+            NodeKind::Dir { children, .. } => children.get(name).copied(),
+            NodeKind::File { .. } => None,
+        }
+    }
+
+    /// Full path, e.g. "/a/e" ("/" for the root)
+    pub fn path(&self, mut id: NodeId) -> String {
+        let mut names = vec![];
+        while id != ROOT {
+            names.push(self.nodes[id].name.as_str());
+            id = self.nodes[id].parent;
+        }
+        names.reverse();
+        format!("/{}", names.join("/"))
+    }
+
+    /// Follows `path` from `cwd`. Absolute paths start at the root, `..` goes up and `.` stays.
+    pub fn resolve(&self, cwd: NodeId, path: &str) -> Result<NodeId, FsError> {
+        let mut id = if path.starts_with('/') { ROOT } else { cwd };
+        for name in path.split('/').filter(|n| !n.is_empty() && *n != ".") {
+            if !self.nodes[id].is_dir() {
+                return Err(FsError::NotADirectory(self.path(id)));
+            }
+            id = match name {
+                ".." => self.nodes[id].parent,
+                _ => self
+                    .child(id, name)
+                    .ok_or_else(|| FsError::NotFound(self.join(id, name)))?,
+            };
+        }
+        Ok(id)
+    }
+
+    /// Looks up an absolute path
+    #[cfg_attr(not(test), allow(dead_code))]
+    pub fn lookup(&self, path: &str) -> Result<NodeId, FsError> {
+        self.resolve(ROOT, path)
+    }
+
+    fn join(&self, dir: NodeId, name: &str) -> String {
+        match dir {
+            ROOT => format!("/{name}"),
+            _ => format!("{}/{name}", self.path(dir)),
+        }
+    }
+
+    fn add(&mut self, dir: NodeId, name: &str, kind: NodeKind) -> Result<NodeId, FsError> {
+        if let Some(existing) = self.child(dir, name) {
+            // Seeing the same thing again is fine
+            return match (&self.nodes[existing].kind, &kind) {
+                (NodeKind::Dir { .. }, NodeKind::Dir { .. }) => Ok(existing),
+                (NodeKind::File { size: a }, NodeKind::File { size: b }) if a == b => Ok(existing),
+                _ => Err(FsError::Conflict(self.join(dir, name))),
+            };
+        }
+        let id = self.nodes.len();
+        match &mut self.nodes[dir].kind {
+            NodeKind::Dir { children, .. } => children.insert(name.to_string(), id),
+            NodeKind::File { .. } => return Err(FsError::NotADirectory(self.path(dir))),
+        };
+        self.nodes.push(Node {
+            name: name.to_string(),
+            parent: dir,
+            kind,
+        });
+        Ok(id)
+    }
+
+    pub fn add_dir(&mut self, dir: NodeId, name: &str) -> Result<NodeId, FsError> {
+        self.add(
+            dir,
+            name,
+            NodeKind::Dir {
+                children: BTreeMap::new(),
+                listed: false,
+            },
+        )
+    }
+
+    pub fn add_file(&mut self, dir: NodeId, name: &str, size: usize) -> Result<NodeId, FsError> {
+        self.add(dir, name, NodeKind::File { size })
+    }
+
+    /// Total size of every node (directories include everything below them), indexed by NodeId
+    pub fn sizes(&self) -> Vec<usize> {
+        let mut sizes: Vec<usize> = self
+            .nodes
+            .iter()
+            .map(|n| match n.kind {
+                NodeKind::File { size } => size,
+                NodeKind::Dir { .. } => 0,
+            })
+            .collect();
+        // Children come after their parents, so going backwards finishes each child first
+        for id in (1..self.nodes.len()).rev() {
+            sizes[self.nodes[id].parent] += sizes[id];
+        }
+        sizes
+    }
+
+    /// Every directory as (id, full path, total size), in the order they were found
+    pub fn dirs(&self) -> impl Iterator<Item = (NodeId, String, usize)> + '_ {
+        let sizes = self.sizes();
+        (0..self.nodes.len())
+            .filter(|id| self.nodes[*id].is_dir())
+            .map(move |id| (id, self.path(id), sizes[id]))
+    }
+
+    /// Drawn like the puzzle's example, e.g. "- a (dir)" and "  - i (file, size=584)"
+    pub fn tree(&self) -> String {
+        let mut tree = String::new();
+        self.write_tree(&mut tree, ROOT, 0);
+        tree
+    }
+
+    fn write_tree(&self, tree: &mut String, id: NodeId, depth: usize) {
+        let node = &self.nodes[id];
+        let name = if id == ROOT { "/" } else { &node.name };
+        match node.kind {
+            NodeKind::Dir { .. } => {
+                writeln!(tree, "{:depth$}- {name} (dir)", "", depth = depth * 2)
+            }
+            NodeKind::File { size } => writeln!(
+                tree,
+                "{:depth$}- {name} (file, size={size})",
+                "",
+                depth = depth * 2
+            ),
+        }
+        .unwrap();
+        for (_, child) in self.children(id) {
+            self.write_tree(tree, child, depth + 1);
+        }
+    }
+
+    /// Every directory as (full path, total size), biggest first
+    pub fn du(&self) -> Vec<(String, usize)> {
+        let mut du: Vec<(String, usize)> =
+            self.dirs().map(|(_, path, size)| (path, size)).collect();
+        du.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        du
+    }
+
+    /// du as a table, one "size  path" line per directory
+    pub fn du_table(&self) -> String {
+        self.du()
+            .iter()
+            .map(|(path, size)| format!("{size:>10}  {path}\n"))
+            .collect()
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum ReplayErrorKind {
+    UnknownCommand(String),
+    /// Output that doesn't follow an `ls`
+    UnexpectedOutput(String),
+    BadListing(String),
+    /// A second `ls` of a directory that doesn't match the first
+    ListingChanged(String),
+    Fs(FsError),
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct ReplayError {
+    /// Starts at 1
+    pub line: usize,
+    pub kind: ReplayErrorKind,
+}
+
+impl fmt::Display for ReplayError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}: ", self.line)?;
+        match &self.kind {
+            ReplayErrorKind::UnknownCommand(command) => write!(f, "unknown command '{command}'"),
+            ReplayErrorKind::UnexpectedOutput(line) => write!(f, "'{line}' isn't output of an ls"),
+            ReplayErrorKind::BadListing(line) => write!(f, "can't read ls output '{line}'"),
+            ReplayErrorKind::ListingChanged(path) => {
+                write!(f, "listing of '{path}' differs from the earlier one")
+            }
+            ReplayErrorKind::Fs(e) => write!(f, "{e}"),
+        }
+    }
+}
+
+impl Error for ReplayError {}
+
+/// Rebuilds the filesystem a terminal transcript explored, checking it's consistent
+pub fn replay(transcript: &str) -> Result<FileSystem, ReplayError> {
+    let mut fs = FileSystem::new();
+    let mut cwd = ROOT;
+    let mut listing: Option<Listing> = None;
+
+    for (ndx, line) in transcript.lines().enumerate() {
+        let line_no = ndx + 1;
+        let error = |kind| ReplayError {
+            line: line_no,
+            kind,
+        };
+        if line.trim().is_empty() {
+            continue;
+        }
+        if let Some(command) = line.strip_prefix("$ ") {
+            if let Some(listing) = listing.take() {
+                finish_listing(&mut fs, listing)?;
+            }
+            match command.split_once(' ') {
+                Some(("cd", path)) => {
+                    cwd = fs
+                        .resolve(cwd, path)
+                        .map_err(|e| error(ReplayErrorKind::Fs(e)))?;
+                    if !fs.node(cwd).is_dir() {
+                        return Err(error(ReplayErrorKind::Fs(FsError::NotADirectory(
+                            fs.path(cwd),
+                        ))));
+                    }
+                }
+                None if command == "ls" => {
+                    listing = Some(Listing {
+                        dir: cwd,
+                        line: line_no,
+                        first_new: fs.len(),
+                        entries: vec![],
+                    })
+                }
+                _ => return Err(error(ReplayErrorKind::UnknownCommand(command.to_string()))),
+            }
+            continue;
+        }
+
+        let Some(Listing { dir, entries, .. }) = listing.as_mut() else {
+            return Err(error(ReplayErrorKind::UnexpectedOutput(line.to_string())));
+        };
+        let id = match line.split_once(' ') {
+            Some(("dir", name)) => fs.add_dir(*dir, name),
+            Some((size, name)) => match size.parse() {
+                Ok(size) => fs.add_file(*dir, name, size),
+                Err(_) => return Err(error(ReplayErrorKind::BadListing(line.to_string()))),
+            },
+            None => return Err(error(ReplayErrorKind::BadListing(line.to_string()))),
+        }
+        .map_err(|e| error(ReplayErrorKind::Fs(e)))?;
+        entries.push(id);
+    }
+    if let Some(listing) = listing {
+        finish_listing(&mut fs, listing)?;
+    }
+    Ok(fs)
+}
+
+/// An `ls` whose output is being read
+struct Listing {
+    dir: NodeId,
+    /// Of the `ls`
+    line: usize,
+    /// Nodes from here on were added by this listing
+    first_new: NodeId,
+    entries: Vec<NodeId>,
+}
+
+// Marks the directory listed, or checks the listing matches if it already was
+fn finish_listing(fs: &mut FileSystem, mut listing: Listing) -> Result<(), ReplayError> {
+    listing.entries.sort_unstable();
+    listing.entries.dedup();
+    let changed = listing.entries.len() != fs.children(listing.dir).count()
+        || listing.entries.iter().any(|id| *id >= listing.first_new);
+    let path = fs.path(listing.dir);
+    let NodeKind::Dir { listed, .. } = &mut fs.nodes[listing.dir].kind else {
+        unreachable!("Only directories are listed");
+    };
+    if *listed && changed {
+        return Err(ReplayError {
+            line: listing.line,
+            kind: ReplayErrorKind::ListingChanged(path),
+        });
+    }
+    *listed = true;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{replay, FileSystem, FsError, ReplayError, ReplayErrorKind, ROOT};
+
+    #[test]
+    fn test_resolve() {
+        let mut fs = FileSystem::new();
+        let a = fs.add_dir(ROOT, "a").unwrap();
+        let e = fs.add_dir(a, "e").unwrap();
+        let i = fs.add_file(e, "i", 584).unwrap();
+        fs.add_file(ROOT, "b.txt", 14848514).unwrap();
+
+        assert_eq!(fs.lookup("/a/e/i"), Ok(i));
+        assert_eq!(fs.resolve(e, ".."), Ok(a));
+        assert_eq!(fs.resolve(e, "../../.."), Ok(ROOT));
+        assert_eq!(fs.resolve(e, "/a/./e/../e/i"), Ok(i));
+        assert_eq!(fs.resolve(a, "e/i"), Ok(i));
+        assert_eq!(fs.path(i), "/a/e/i");
+        assert_eq!(fs.path(ROOT), "/");
+        assert_eq!(
+            fs.lookup("/a/x"),
+            Err(FsError::NotFound("/a/x".to_string()))
+        );
+        assert_eq!(
+            fs.lookup("/b.txt/x"),
+            Err(FsError::NotADirectory("/b.txt".to_string()))
+        );
+        assert_eq!(fs.add_dir(a, "e"), Ok(e));
+        assert_eq!(
+            fs.add_file(e, "i", 1),
+            Err(FsError::Conflict("/a/e/i".to_string()))
+        );
+
+        assert_eq!(fs.sizes()[ROOT], 14848514 + 584);
+        assert_eq!(fs.sizes()[a], 584);
+        let dirs: Vec<_> = fs.dirs().map(|(_, path, size)| (path, size)).collect();
+        assert_eq!(
+            dirs,
+            vec![
+                ("/".to_string(), 14848514 + 584),
+                ("/a".to_string(), 584),
+                ("/a/e".to_string(), 584)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_reports() {
+        let fs = replay("$ cd /\n$ ls\ndir a\n14848514 b.txt\n$ cd a\n$ ls\ndir e\n29116 f\n$ cd e\n$ ls\n584 i")
+            .unwrap();
+        assert_eq!(
+            fs.tree(),
+            "- / (dir)\n  - a (dir)\n    - e (dir)\n      - i (file, size=584)\n    - f (file, size=29116)\n  - b.txt (file, size=14848514)\n"
+        );
+        assert_eq!(
+            fs.du_table(),
+            "  14878214  /\n     29700  /a\n       584  /a/e\n"
+        );
+    }
+
+    #[test]
+    fn test_replay() {
+        let fs = replay("$ cd /\n$ ls\ndir a\n1 b\n$ cd a\n$ ls\n2 c\n$ cd /\n$ ls\n1 b\ndir a\n")
+            .unwrap();
+        assert_eq!(fs.sizes()[ROOT], 3);
+        assert_eq!(fs.lookup("/a/c").map(|c| fs.sizes()[c]), Ok(2));
+
+        assert_eq!(
+            replay("$ cd /\n$ cd x").unwrap_err(),
+            ReplayError {
+                line: 2,
+                kind: ReplayErrorKind::Fs(FsError::NotFound("/x".to_string()))
+            }
+        );
+        assert_eq!(
+            replay("$ ls\ndir a\n$ ls\ndir a\n1 b").unwrap_err(),
+            ReplayError {
+                line: 3,
+                kind: ReplayErrorKind::ListingChanged("/".to_string())
+            }
+        );
+        assert_eq!(replay("$ ls\ndir a\n1 b\n$ ls\n1 b").unwrap_err().line, 4);
+        assert_eq!(
+            replay("$ ls\ndir a\n$ ls\n5 a").unwrap_err().kind,
+            ReplayErrorKind::Fs(FsError::Conflict("/a".to_string()))
+        );
+        assert_eq!(
+            replay("dir a").unwrap_err().kind,
+            ReplayErrorKind::UnexpectedOutput("dir a".to_string())
+        );
+        assert_eq!(
+            replay("$ rm -rf /").unwrap_err().to_string(),
+            "line 1: unknown command 'rm -rf /'"
+        );
+    }
+}
@@ -116,7 +116,7 @@ fn solve2(grid: &mut StaticGrid<TreeCell>) -> usize {
                     .skip(1)
                     .take_until(|&c| c.height >= cell.height)
                     .count();
-                let cell = grid.get_cell_mut(y, x).unwrap();
+                let cell = grid.get_cell_mut(x, y).unwrap();
                 cell.viewing_score = north_value * south_value * east_value * west_value;
                 if cell.viewing_score > max_value {
                     max_value = cell.viewing_score;
@@ -1,33 +1,63 @@
 use regex::Regex;
-use std::{fmt, ops::Sub};
+use std::{collections::HashSet, fmt, iter, ops::Sub};
 
 use vector2d::Vector2D;
 
-use utils::{Direction, DynamicGrid, SliceExt, Grid};
+use utils::{Direction, SliceExt};
 
 struct Instruction {
     direction: Direction,
     distance: usize,
 }
 
-struct Rope {
-    knots: Vec<Vector2D<isize>>,
-    grid: DynamicGrid<RopeCell>,
+impl fmt::Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let direction = match self.direction {
+            Direction::North => "U",
+            Direction::South => "D",
+            Direction::East => "R",
+            Direction::West => "L",
+            Direction::NorthEast => "UR",
+            Direction::NorthWest => "UL",
+            Direction::SouthEast => "DR",
+            Direction::SouthWest => "DL",
+        };
+        write!(f, "{direction} {}", self.distance)
+    }
 }
 
 type Point = Vector2D<isize>;
 
+/// Where every knot is after one step of the head
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Frame {
+    /// Index of the instruction being carried out
+    instruction: usize,
+    /// Head first
+    knots: Vec<Point>,
+}
+
+struct Rope {
+    /// Head first
+    knots: Vec<Point>,
+    /// Every position each knot has been in, head first
+    visited: Vec<HashSet<Point>>,
+}
+
 impl Rope {
     pub fn new(num_knots: usize) -> Self {
-        let mut g = DynamicGrid::<RopeCell>::new(0,0);
-        let c = g.get_cell_mut(0, 0).unwrap();
-        c.tail_visited = true;
+        assert!(num_knots > 0, "A rope needs at least a head");
+        let start = Vector2D { x: 0, y: 0 };
         Rope {
-            knots: vec![Vector2D { x: 0, y: 0 }; num_knots],
-            grid: g
+            knots: vec![start; num_knots],
+            visited: vec![HashSet::from([start]); num_knots]
         }
     }
 
+    pub fn tail_visited(&self) -> &HashSet<Point> {
+        self.visited.last().unwrap()
+    }
+
     /// return direction the head is in from tail
     fn get_direction(knot_a: &Point, knot_b: &Point) -> Option<Direction> {
         let dir = match knot_a.y.cmp(&knot_b.y) {
@@ -55,46 +85,49 @@ impl Rope {
     }
 
     pub fn move_head(&mut self, direction: &Direction, amount: usize) {
-        let amount = amount as isize;
         for _ in 0..amount {
-            let head = self.knots.get_mut(0).unwrap();
-            match direction {
-                Direction::North => {
-                    head.y -= 1;
-                },
-                Direction::South => {
-                    head.y += 1;
-                },
-                Direction::East => {
-                    head.x += 1;
-                },
-                Direction::West => {
-                    head.x -= 1;
-                },
-                Direction::NorthEast => {
-                    head.y -= 1;
-                    head.x += 1;
-                },
-                Direction::NorthWest => {
-                    head.y -= 1;
-                    head.x -= 1;
-                },
-                Direction::SouthEast => {
-                    head.y += 1;
-                    head.x += 1;
-                },
-                Direction::SouthWest => {
-                    head.y += 1;
-                    head.x -= 1;
-                }
+            self.step(direction);
+        }
+    }
+
+    /// Moves the head one step (diagonals included), and the rest of the rope after it
+    pub fn step(&mut self, direction: &Direction) {
+        let head = self.knots.get_mut(0).unwrap();
+        match direction {
+            Direction::North => {
+                head.y -= 1;
+            },
+            Direction::South => {
+                head.y += 1;
+            },
+            Direction::East => {
+                head.x += 1;
+            },
+            Direction::West => {
+                head.x -= 1;
+            },
+            Direction::NorthEast => {
+                head.y -= 1;
+                head.x += 1;
+            },
+            Direction::NorthWest => {
+                head.y -= 1;
+                head.x -= 1;
+            },
+            Direction::SouthEast => {
+                head.y += 1;
+                head.x += 1;
+            },
+            Direction::SouthWest => {
+                head.y += 1;
+                head.x -= 1;
             }
-            let c = self.grid.get_cell_mut(head.x, head.y).unwrap();
-            c.head_visited = true;
-            for ndx in 0..self.knots.len()-1 {
-                self.move_knot(ndx+1, ndx);
+        }
+        self.visited[0].insert(self.knots[0]);
+        for ndx in 0..self.knots.len()-1 {
+            if self.move_knot(ndx+1, ndx) {
+                self.visited[ndx+1].insert(self.knots[ndx+1]);
             }
-            let c = self.grid.get_cell_mut(self.knots.last().unwrap().x, self.knots.last().unwrap().y).unwrap();
-            c.tail_visited = true;
         }
     }
 
@@ -174,22 +207,167 @@ impl Rope {
     }
 }
 
-#[derive(Clone, Default)]
-struct RopeCell {
-    tail_visited: bool,
-    head_visited: bool,
+// For looking at any knot, not just the tail
+impl Rope {
+    pub fn knots(&self) -> &[Point] {
+        &self.knots
+    }
+
+    /// Positions knot `ndx` (0 is the head) has been in
+    pub fn visited(&self, ndx: usize) -> &HashSet<Point> {
+        &self.visited[ndx]
+    }
+
+    /// Carries out the instructions, yielding the knots after every step
+    pub fn frames<'a>(&'a mut self, instructions: &'a [Instruction]) -> impl Iterator<Item = Frame> + 'a {
+        instructions
+            .iter()
+            .enumerate()
+            .flat_map(|(ndx, instruction)| iter::repeat_n((ndx, &instruction.direction), instruction.distance))
+            .map(move |(ndx, direction)| {
+                self.step(direction);
+                Frame { instruction: ndx, knots: self.knots.clone() }
+            })
+    }
 }
 
-impl fmt::Display for RopeCell {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "{:>1} ", if self.tail_visited { '#' } else { '.' })
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum KnotView {
+    All,
+    HeadAndTail,
+}
+
+/// Area to draw, corners included
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct Viewport {
+    min: Point,
+    max: Point,
+}
+
+impl Viewport {
+    /// The smallest area showing all the points
+    fn fit<'a>(points: impl IntoIterator<Item = &'a Point>) -> Viewport {
+        let start = Vector2D { x: 0, y: 0 };
+        points.into_iter().fold(Viewport { min: start, max: start }, |v, p| Viewport {
+            min: Vector2D { x: v.min.x.min(p.x), y: v.min.y.min(p.y) },
+            max: Vector2D { x: v.max.x.max(p.x), y: v.max.y.max(p.y) },
+        })
+    }
+}
+
+/// Draws the rope like the puzzle's diagrams: H for the head, then 1, 2, ... (or T for a
+/// two knot rope), s for the start and . for everything else
+#[derive(Clone, Copy, Debug)]
+struct Renderer {
+    knots: KnotView,
+    /// Fits the rope if not set
+    viewport: Option<Viewport>,
+    /// Draw every step, or only the end of each move
+    every_step: bool,
+    /// Add "(H covers T, s)" notes
+    annotate: bool,
+}
+
+impl Default for Renderer {
+    fn default() -> Self {
+        Renderer { knots: KnotView::All, viewport: None, every_step: true, annotate: true }
+    }
+}
+
+impl Renderer {
+    fn label(ndx: usize, num_knots: usize) -> char {
+        match ndx {
+            0 => 'H',
+            _ if num_knots == 2 => 'T',
+            _ => char::from_digit(ndx as u32, 36).unwrap_or('?'),
+        }
+    }
+
+    /// One rope, as lines of text
+    fn draw(&self, knots: &[Point]) -> String {
+        let viewport = self.viewport.unwrap_or_else(|| Viewport::fit(knots));
+        let shown: Vec<usize> = match self.knots {
+            KnotView::All => (0..knots.len()).collect(),
+            KnotView::HeadAndTail => vec![0, knots.len() - 1],
+        };
+        let mut lines = vec![];
+        for y in viewport.min.y..=viewport.max.y {
+            let mut line = String::new();
+            let mut notes = vec![];
+            for x in viewport.min.x..=viewport.max.x {
+                // Everything here, in drawing order
+                let mut here: Vec<char> = shown
+                    .iter()
+                    .filter(|ndx| knots[**ndx] == Vector2D { x, y })
+                    .map(|ndx| Renderer::label(*ndx, knots.len()))
+                    .collect();
+                if x == 0 && y == 0 {
+                    here.push('s');
+                }
+                line.push(here.first().copied().unwrap_or('.'));
+                if here.len() > 1 {
+                    let covered: Vec<String> = here[1..].iter().map(char::to_string).collect();
+                    notes.push(format!("{} covers {}", here[0], covered.join(", ")));
+                }
+            }
+            if self.annotate && !notes.is_empty() {
+                line.push_str(&format!("  ({})", notes.join("; ")));
+            }
+            lines.push(line);
+        }
+        lines.join("\n")
+    }
+
+    /// The puzzle's walkthrough: "== Initial State ==" then a "== R 4 ==" section per move
+    fn draw_moves(&self, instructions: &[Instruction], num_knots: usize) -> String {
+        let mut rope = Rope::new(num_knots);
+        let initial = rope.knots().to_vec();
+        let frames: Vec<Frame> = rope.frames(instructions).collect();
+        // Keep the same area for every frame
+        let renderer = Renderer {
+            viewport: Some(self.viewport.unwrap_or_else(|| {
+                Viewport::fit(frames.iter().flat_map(|f| &f.knots))
+            })),
+            ..*self
+        };
+
+        let mut walkthrough = format!("== Initial State ==\n\n{}\n\n", renderer.draw(&initial));
+        for (ndx, instruction) in instructions.iter().enumerate() {
+            walkthrough.push_str(&format!("== {instruction} ==\n\n"));
+            let steps: Vec<&Frame> = frames.iter().filter(|f| f.instruction == ndx).collect();
+            let shown = match self.every_step {
+                true => &steps[..],
+                false => &steps[steps.len().saturating_sub(1)..],
+            };
+            for frame in shown {
+                walkthrough.push_str(&format!("{}\n\n", renderer.draw(&frame.knots)));
+            }
+        }
+        walkthrough
+    }
+
+    /// Where a knot has been, as # (and s for the start)
+    fn draw_visited(&self, visited: &HashSet<Point>) -> String {
+        let viewport = self.viewport.unwrap_or_else(|| Viewport::fit(visited));
+        (viewport.min.y..=viewport.max.y)
+            .map(|y| {
+                (viewport.min.x..=viewport.max.x)
+                    .map(|x| match (x, y) {
+                        (0, 0) => 's',
+                        _ if visited.contains(&Vector2D { x, y }) => '#',
+                        _ => '.',
+                    })
+                    .collect::<String>()
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
     }
 }
 
 fn parser(s: String) -> Vec<Instruction> {
-    let re = Regex::new(r"(U|D|L|R) (\d+)").unwrap();
+    // Diagonals too, e.g. "UR 3"
+    let re = Regex::new(r"(UR|UL|DR|DL|U|D|L|R) (\d+)").unwrap();
     s.split('\n')
-        .into_iter()
         .map(|l| {
             let caps = re.captures(l).unwrap();
             let direction = match caps.get(1).unwrap().as_str() {
@@ -197,6 +375,10 @@ fn parser(s: String) -> Vec<Instruction> {
                 "D" => Direction::South,
                 "L" => Direction::West,
                 "R" => Direction::East,
+                "UR" => Direction::NorthEast,
+                "UL" => Direction::NorthWest,
+                "DR" => Direction::SouthEast,
+                "DL" => Direction::SouthWest,
                 _ => panic!("This should not happen..."),
             };
             Instruction {
@@ -207,13 +389,12 @@ fn parser(s: String) -> Vec<Instruction> {
         .collect()
 }
 
-fn solve(instructions: &Vec<Instruction>, num_knots: usize) -> usize {
+fn solve(instructions: &[Instruction], num_knots: usize) -> usize {
     let mut rope = Rope::new(num_knots);
     for instruction in instructions {
         rope.move_head(&instruction.direction, instruction.distance);
     }
-    println!("{}", rope.grid);
-    rope.grid.cell_iter().filter(|&c| c.tail_visited).count()
+    rope.tail_visited().len()
 }
 
 fn main() {
@@ -223,11 +404,48 @@ fn main() {
 
     let total_visited = solve(&instructions, 10);
     println!("Solution 2: Visited {total_visited} spaces.");
+
+    let args: Vec<String> = std::env::args().collect();
+    let knots = if args.iter().any(|arg| arg == "--draw") {
+        Some(KnotView::All)
+    } else if args.iter().any(|arg| arg == "--draw-ends") {
+        Some(KnotView::HeadAndTail)
+    } else {
+        None
+    };
+    if let Some(knots) = knots {
+        let renderer = Renderer { knots, every_step: false, ..Renderer::default() };
+        print!("{}", renderer.draw_moves(&instructions, 10));
+        let mut rope = Rope::new(10);
+        for instruction in &instructions {
+            rope.move_head(&instruction.direction, instruction.distance);
+        }
+        println!("{}", renderer.draw_visited(rope.visited(9)));
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::{parser, solve};
+    use vector2d::Vector2D;
+
+    use crate::{parser, solve, KnotView, Renderer, Rope, Viewport};
+
+    const PUZZLE: &str = include_str!("../puzzle.txt");
+
+    // The puzzle text from `from` up to `to`, searching after `after`
+    fn section(after: usize, from: &str, to: &str) -> String {
+        let text = &PUZZLE[PUZZLE[after..].find(from).unwrap() + after..];
+        text[..text.find(to).unwrap()].to_string()
+    }
+
+    const LARGE: &str = "R 5
+U 8
+L 8
+D 3
+R 17
+D 10
+L 25
+U 20";
 
     #[test]
     fn test_puzzle() {
@@ -250,4 +468,70 @@ U 20".to_string();
         let solution = solve(&test_data, 10);
         assert_eq!(solution, 36);
     }
+
+    #[test]
+    fn test_knots() {
+        let test_data = utils::load_puzzle_test(9, parser);
+        let mut rope = Rope::new(10);
+        let frames: Vec<_> = rope.frames(&test_data).collect();
+        assert_eq!(frames.len(), 4 + 4 + 3 + 1 + 4 + 1 + 5 + 2);
+        assert_eq!(frames[3].instruction, 0);
+        assert_eq!(frames[4].instruction, 1);
+        // After "R 4" the knots are H, 1, 2, 3 and 4 in a row, the rest still at the start
+        assert_eq!(frames[3].knots[..5], (0..5).rev().map(|x| Vector2D { x, y: 0 }).collect::<Vec<_>>()[..]);
+        assert_eq!(rope.knots()[0], Vector2D { x: 2, y: -2 });
+        assert_eq!(rope.tail_visited().len(), 1);
+        assert_eq!(rope.visited(1).len(), 13);
+    }
+
+    #[test]
+    fn test_diagonal() {
+        let mut rope = Rope::new(2);
+        for instruction in parser("UR 3\nDL 1".to_string()) {
+            rope.move_head(&instruction.direction, instruction.distance);
+        }
+        assert_eq!(rope.knots(), [Vector2D { x: 2, y: -2 }, Vector2D { x: 2, y: -2 }]);
+        assert_eq!(rope.tail_visited().len(), 3);
+        assert_eq!(rope.visited(0).len(), 4);
+    }
+
+    #[test]
+    fn test_draw() {
+        let test_data = utils::load_puzzle_test(9, parser);
+        let small = Renderer {
+            viewport: Some(Viewport { min: Vector2D { x: 0, y: -4 }, max: Vector2D { x: 5, y: 0 } }),
+            ..Renderer::default()
+        };
+        let part1 = section(0, "== Initial State ==", "After simulating");
+        assert_eq!(small.draw_moves(&test_data, 2), part1);
+        let part2 = PUZZLE.find("--- Part Two ---").unwrap();
+        assert_eq!(
+            small.draw_moves(&test_data, 10),
+            section(part2, "== Initial State ==", "Now, you need")
+        );
+
+        let large = Renderer {
+            viewport: Some(Viewport { min: Vector2D { x: -11, y: -15 }, max: Vector2D { x: 14, y: 5 } }),
+            every_step: false,
+            ..Renderer::default()
+        };
+        let large_example = PUZZLE.find("Here's a larger example").unwrap();
+        let test_data = parser(LARGE.to_string());
+        assert_eq!(
+            large.draw_moves(&test_data, 10),
+            section(large_example, "== Initial State ==", "Now, the tail")
+        );
+        let mut rope = Rope::new(10);
+        for instruction in &test_data {
+            rope.move_head(&instruction.direction, instruction.distance);
+        }
+        let visited = section(large_example, "Now, the tail", "Simulate");
+        let visited = visited.split_once("\n\n").unwrap().1;
+        assert_eq!(large.draw_visited(rope.tail_visited()) + "\n\n", visited);
+
+        // Only the ends, fitted to the rope
+        let ends = Renderer { knots: KnotView::HeadAndTail, annotate: false, ..Renderer::default() };
+        assert_eq!(ends.draw(&[Vector2D { x: 2, y: -1 }, Vector2D { x: 1, y: 0 }, Vector2D { x: 1, y: 0 }]), "..H\ns2.");
+        assert_eq!(ends.draw_visited(&[Vector2D { x: 1, y: 1 }].into()), "s.\n.#");
+    }
 }
@@ -0,0 +1,339 @@
+use std::{
+    fmt,
+    ops::{BitAnd, BitOr, BitXor, Sub},
+};
+
+/// a-z = 1-26, A-Z = 27-52
+pub fn letter_priority(c: char) -> Option<usize> {
+    match c {
+        'a'..='z' => Some(c as usize - 'a' as usize + 1),
+        'A'..='Z' => Some(c as usize - 'A' as usize + 27),
+        _ => None,
+    }
+}
+
+/// Inverse of letter_priority
+pub fn priority_letter(priority: usize) -> Option<char> {
+    match priority {
+        1..=26 => Some((b'a' + (priority - 1) as u8) as char),
+        27..=52 => Some((b'A' + (priority - 27) as u8) as char),
+        _ => None,
+    }
+}
+
+// The fixed size sets only differ by their backing integer
+macro_rules! fixed_bitset {
+    ($name:ident, $int:ty) => {
+        /// A set of small numbers (0..BITS), one bit per number
+        #[derive(Clone, Copy, Default, PartialEq, Eq, Hash)]
+        pub struct $name(pub $int);
+
+        impl $name {
+            pub const BITS: usize = <$int>::BITS as usize;
+
+            pub fn new() -> Self {
+                $name(0)
+            }
+
+            /// The set of letter priorities (see letter_priority) in `s`, other chars are ignored
+            pub fn from_letters(s: &str) -> Self {
+                s.chars().filter_map(letter_priority).collect()
+            }
+
+            /// Returns if the value was newly inserted
+            pub fn insert(&mut self, value: usize) -> bool {
+                assert!(
+                    value < Self::BITS,
+                    "{value} does not fit in {}",
+                    stringify!($name)
+                );
+                let was_set = self.contains(value);
+                self.0 |= 1 << value;
+                !was_set
+            }
+
+            /// Returns if the value was present
+            pub fn remove(&mut self, value: usize) -> bool {
+                let was_set = self.contains(value);
+                if was_set {
+                    self.0 &= !(1 << value);
+                }
+                was_set
+            }
+
+            pub fn contains(&self, value: usize) -> bool {
+                value < Self::BITS && self.0 & (1 << value) != 0
+            }
+
+            pub fn len(&self) -> usize {
+                self.0.count_ones() as usize
+            }
+
+            pub fn is_empty(&self) -> bool {
+                self.0 == 0
+            }
+
+            pub fn clear(&mut self) {
+                self.0 = 0;
+            }
+
+            pub fn union(&self, other: &Self) -> Self {
+                $name(self.0 | other.0)
+            }
+
+            pub fn intersection(&self, other: &Self) -> Self {
+                $name(self.0 & other.0)
+            }
+
+            pub fn difference(&self, other: &Self) -> Self {
+                $name(self.0 & !other.0)
+            }
+
+            pub fn symmetric_difference(&self, other: &Self) -> Self {
+                $name(self.0 ^ other.0)
+            }
+
+            pub fn is_subset(&self, other: &Self) -> bool {
+                self.0 & !other.0 == 0
+            }
+
+            /// Smallest value in the set
+            pub fn first(&self) -> Option<usize> {
+                (!self.is_empty()).then(|| self.0.trailing_zeros() as usize)
+            }
+
+            /// Iterates the values in ascending order
+            pub fn iter(&self) -> impl Iterator<Item = usize> {
+                let mut bits = self.0;
+                std::iter::from_fn(move || {
+                    if bits == 0 {
+                        return None;
+                    }
+                    let value = bits.trailing_zeros() as usize;
+                    // Clear the lowest set bit
+                    bits &= bits - 1;
+                    Some(value)
+                })
+            }
+        }
+
+        impl FromIterator<usize> for $name {
+            fn from_iter<I: IntoIterator<Item = usize>>(iter: I) -> Self {
+                let mut set = $name::new();
+                for value in iter {
+                    set.insert(value);
+                }
+                set
+            }
+        }
+
+        impl BitAnd for $name {
+            type Output = Self;
+
+            fn bitand(self, rhs: Self) -> Self {
+                self.intersection(&rhs)
+            }
+        }
+
+        impl BitOr for $name {
+            type Output = Self;
+
+            fn bitor(self, rhs: Self) -> Self {
+                self.union(&rhs)
+            }
+        }
+
+        impl BitXor for $name {
+            type Output = Self;
+
+            fn bitxor(self, rhs: Self) -> Self {
+                self.symmetric_difference(&rhs)
+            }
+        }
+
+        impl Sub for $name {
+            type Output = Self;
+
+            fn sub(self, rhs: Self) -> Self {
+                self.difference(&rhs)
+            }
+        }
+
+        impl fmt::Debug for $name {
+            fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.debug_set().entries(self.iter()).finish()
+            }
+        }
+    };
+}
+
+fixed_bitset!(BitSet64, u64);
+fixed_bitset!(BitSet128, u128);
+
+/// A set of numbers, one bit per number, that grows to fit the largest value
+#[derive(Clone, Default)]
+pub struct BitSet {
+    words: Vec<u64>,
+}
+
+impl BitSet {
+    pub fn new() -> Self {
+        BitSet { words: vec![] }
+    }
+
+    pub fn with_capacity(num_bits: usize) -> Self {
+        BitSet {
+            words: vec![0; num_bits.div_ceil(64)],
+        }
+    }
+
+    /// Returns if the value was newly inserted
+    pub fn insert(&mut self, value: usize) -> bool {
+        let (word, bit) = (value / 64, value % 64);
+        if word >= self.words.len() {
+            self.words.resize(word + 1, 0);
+        }
+        let was_set = self.words[word] & (1 << bit) != 0;
+        self.words[word] |= 1 << bit;
+        !was_set
+    }
+
+    /// Returns if the value was present
+    pub fn remove(&mut self, value: usize) -> bool {
+        let was_set = self.contains(value);
+        if was_set {
+            self.words[value / 64] &= !(1 << (value % 64));
+        }
+        was_set
+    }
+
+    pub fn contains(&self, value: usize) -> bool {
+        self.words
+            .get(value / 64)
+            .is_some_and(|w| w & (1 << (value % 64)) != 0)
+    }
+
+    pub fn len(&self) -> usize {
+        self.words.iter().map(|w| w.count_ones() as usize).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.words.iter().all(|&w| w == 0)
+    }
+
+    pub fn clear(&mut self) {
+        self.words.clear();
+    }
+
+    pub fn union(&self, other: &Self) -> Self {
+        let (longer, shorter) = if self.words.len() >= other.words.len() {
+            (self, other)
+        } else {
+            (other, self)
+        };
+        let mut words = longer.words.clone();
+        for (w, o) in words.iter_mut().zip(shorter.words.iter()) {
+            *w |= o;
+        }
+        BitSet { words }
+    }
+
+    pub fn intersection(&self, other: &Self) -> Self {
+        BitSet {
+            words: self
+                .words
+                .iter()
+                .zip(other.words.iter())
+                .map(|(w, o)| w & o)
+                .collect(),
+        }
+    }
+
+    pub fn difference(&self, other: &Self) -> Self {
+        let mut words = self.words.clone();
+        for (w, o) in words.iter_mut().zip(other.words.iter()) {
+            *w &= !o;
+        }
+        BitSet { words }
+    }
+
+    pub fn is_subset(&self, other: &Self) -> bool {
+        self.difference(other).is_empty()
+    }
+
+    /// Iterates the values in ascending order
+    pub fn iter(&self) -> impl Iterator<Item = usize> + '_ {
+        self.words
+            .iter()
+            .enumerate()
+            .flat_map(|(ndx, &word)| BitSet64(word).iter().map(move |bit| ndx * 64 + bit))
+    }
+}
+
+impl PartialEq for BitSet {
+    // Trailing empty words don't matter
+    fn eq(&self, other: &Self) -> bool {
+        let len = self.words.len().max(other.words.len());
+        (0..len).all(|ndx| self.words.get(ndx).unwrap_or(&0) == other.words.get(ndx).unwrap_or(&0))
+    }
+}
+
+impl Eq for BitSet {}
+
+impl FromIterator<usize> for BitSet {
+    fn from_iter<I: IntoIterator<Item = usize>>(iter: I) -> Self {
+        let mut set = BitSet::new();
+        for value in iter {
+            set.insert(value);
+        }
+        set
+    }
+}
+
+impl fmt::Debug for BitSet {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_set().entries(self.iter()).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{letter_priority, priority_letter, BitSet, BitSet128, BitSet64};
+
+    #[test]
+    fn test_priorities() {
+        assert_eq!(letter_priority('p'), Some(16));
+        assert_eq!(letter_priority('L'), Some(38));
+        assert_eq!(letter_priority('!'), None);
+        assert!(('a'..='z')
+            .chain('A'..='Z')
+            .all(|c| priority_letter(letter_priority(c).unwrap()) == Some(c)));
+    }
+
+    #[test]
+    fn test_fixed() {
+        let first = BitSet64::from_letters("vJrwpWtwJgWr");
+        let second = BitSet64::from_letters("hcsFMMfFFhFp");
+        let shared = first & second;
+        assert_eq!(shared.len(), 1);
+        assert_eq!(shared.first().and_then(priority_letter), Some('p'));
+
+        let mut set: BitSet128 = [1, 100, 127].into_iter().collect();
+        assert!(!set.insert(100));
+        assert!(set.remove(1));
+        assert_eq!(set.iter().collect::<Vec<_>>(), vec![100, 127]);
+        assert!(set.is_subset(&BitSet128::from_iter([1, 100, 127])));
+    }
+
+    #[test]
+    fn test_growable() {
+        let mut a: BitSet = [3, 70, 1000].into_iter().collect();
+        let b: BitSet = [3, 1000, 5000].into_iter().collect();
+        assert_eq!(a.intersection(&b).iter().collect::<Vec<_>>(), vec![3, 1000]);
+        assert_eq!(a.union(&b).len(), 4);
+        assert_eq!(a.difference(&b), BitSet::from_iter([70]));
+        assert!(a.remove(70));
+        assert!(a.is_subset(&b));
+        assert!(!a.contains(5000));
+    }
+}
@@ -0,0 +1,257 @@
+pub mod isa;
+
+use std::fmt::Write;
+
+use isa::{Instruction, InstructionSet, Registers};
+
+/// What the CPU looks like *during* a cycle (before the current instruction finishes)
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CycleState {
+    /// Starts at 1
+    pub cycle: usize,
+    pub registers: Registers,
+    /// Index of the instruction being executed
+    pub pc: usize,
+}
+
+impl CycleState {
+    pub fn register(&self, register: usize) -> isize {
+        self.registers[register]
+    }
+}
+
+/// Gets called during every cycle
+pub trait Observer {
+    fn on_cycle(&mut self, state: &CycleState);
+}
+
+impl<F: FnMut(&CycleState)> Observer for F {
+    fn on_cycle(&mut self, state: &CycleState) {
+        self(state)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Breakpoint {
+    /// Stop after this cycle
+    Cycle(usize),
+    /// Stop after any cycle during which the register has this value
+    Register { register: usize, value: isize },
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StopReason {
+    /// The program ran out of instructions
+    Halted,
+    /// Index into the breakpoints that were hit
+    Breakpoint(usize),
+    /// A run_until condition was met
+    Condition,
+}
+
+pub struct Cpu<'a> {
+    isa: &'a InstructionSet,
+    program: Vec<Instruction>,
+    registers: Registers,
+    /// Number of cycles completed
+    cycles: usize,
+    pc: usize,
+    /// Cycles spent so far on the instruction at pc
+    op_cycles: usize,
+    observers: Vec<&'a mut dyn Observer>,
+    breakpoints: Vec<Breakpoint>,
+    trace: Option<Vec<CycleState>>,
+}
+
+impl<'a> Cpu<'a> {
+    pub fn new(isa: &'a InstructionSet, program: Vec<Instruction>) -> Self {
+        Cpu {
+            isa,
+            program,
+            registers: isa.initial_registers(),
+            cycles: 0,
+            pc: 0,
+            op_cycles: 0,
+            observers: vec![],
+            breakpoints: vec![],
+            trace: None,
+        }
+    }
+
+    pub fn add_observer(&mut self, observer: &'a mut dyn Observer) {
+        self.observers.push(observer);
+    }
+
+    /// Returns the breakpoint's index, reported in StopReason::Breakpoint
+    pub fn add_breakpoint(&mut self, breakpoint: Breakpoint) -> usize {
+        self.breakpoints.push(breakpoint);
+        self.breakpoints.len() - 1
+    }
+
+    pub fn clear_breakpoints(&mut self) {
+        self.breakpoints.clear();
+    }
+
+    /// Start recording every cycle's state
+    pub fn enable_trace(&mut self) {
+        self.trace.get_or_insert_with(Vec::new);
+    }
+
+    pub fn trace(&self) -> &[CycleState] {
+        self.trace.as_deref().unwrap_or_default()
+    }
+
+    /// The recorded state during `cycle`, needs enable_trace
+    pub fn state_at(&self, cycle: usize) -> Option<CycleState> {
+        cycle
+            .checked_sub(1)
+            .and_then(|ndx| self.trace().get(ndx).copied())
+    }
+
+    /// One line per traced cycle, with the registers and the instruction being executed
+    pub fn dump_trace(&self) -> String {
+        let mut dump = String::new();
+        for state in self.trace() {
+            write!(dump, "cycle {:>4} | pc {:>4} |", state.cycle, state.pc).unwrap();
+            for register in 0..self.isa.num_registers() {
+                write!(
+                    dump,
+                    " {} {:>4} |",
+                    self.isa.register_name(register),
+                    state.registers[register]
+                )
+                .unwrap();
+            }
+            writeln!(dump, " {}", self.isa.format(&self.program[state.pc])).unwrap();
+        }
+        dump
+    }
+
+    pub fn register(&self, register: usize) -> isize {
+        self.registers[register]
+    }
+
+    pub fn cycles(&self) -> usize {
+        self.cycles
+    }
+
+    pub fn is_halted(&self) -> bool {
+        self.pc >= self.program.len()
+    }
+
+    /// Runs a single cycle, returning the state during it (None if halted)
+    pub fn step(&mut self) -> Option<CycleState> {
+        let instruction = self.program.get(self.pc)?;
+        self.cycles += 1;
+        let state = CycleState {
+            cycle: self.cycles,
+            registers: self.registers,
+            pc: self.pc,
+        };
+        for observer in self.observers.iter_mut() {
+            observer.on_cycle(&state);
+        }
+        if let Some(trace) = self.trace.as_mut() {
+            trace.push(state);
+        }
+
+        self.op_cycles += 1;
+        if self.op_cycles >= self.isa.cycles(instruction) {
+            // Instruction finished at the end of this cycle
+            self.isa.execute(instruction, &mut self.registers);
+            self.pc += 1;
+            self.op_cycles = 0;
+        }
+        Some(state)
+    }
+
+    /// Runs cycles until the current instruction finishes
+    pub fn step_instruction(&mut self) -> Option<CycleState> {
+        let pc = self.pc;
+        let mut last = None;
+        while self.pc == pc {
+            last = Some(self.step()?);
+        }
+        last
+    }
+
+    /// Runs until `condition` holds after a cycle, a breakpoint is hit, or the program ends
+    pub fn run_until<F: FnMut(&CycleState) -> bool>(&mut self, mut condition: F) -> StopReason {
+        while let Some(state) = self.step() {
+            if let Some(ndx) = self.breakpoints.iter().position(|b| match b {
+                Breakpoint::Cycle(cycle) => state.cycle == *cycle,
+                Breakpoint::Register { register, value } => state.registers[*register] == *value,
+            }) {
+                return StopReason::Breakpoint(ndx);
+            }
+            if condition(&state) {
+                return StopReason::Condition;
+            }
+        }
+        StopReason::Halted
+    }
+
+    /// Runs until a breakpoint is hit or the program ends
+    pub fn run(&mut self) -> StopReason {
+        self.run_until(|_| false)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        isa::{Instruction, InstructionSet, X},
+        Breakpoint, Cpu, CycleState, StopReason,
+    };
+
+    fn small_program(isa: &InstructionSet) -> Vec<Instruction> {
+        isa.parse("noop\naddx 3\naddx -5").unwrap()
+    }
+
+    #[test]
+    fn test_step() {
+        let isa = InstructionSet::handheld();
+        let mut cpu = Cpu::new(&isa, small_program(&isa));
+        let xs: Vec<isize> = std::iter::from_fn(|| cpu.step().map(|s| s.register(X))).collect();
+        assert_eq!(xs, vec![1, 1, 1, 4, 4]);
+        assert_eq!(cpu.register(X), -1);
+        assert!(cpu.is_halted());
+        assert_eq!(cpu.step(), None);
+    }
+
+    #[test]
+    fn test_breakpoints() {
+        let isa = InstructionSet::handheld();
+        let mut cpu = Cpu::new(&isa, small_program(&isa));
+        let cycle_bp = cpu.add_breakpoint(Breakpoint::Cycle(2));
+        let x_bp = cpu.add_breakpoint(Breakpoint::Register {
+            register: X,
+            value: 4,
+        });
+        assert_eq!(cpu.run(), StopReason::Breakpoint(cycle_bp));
+        assert_eq!(cpu.cycles(), 2);
+        assert_eq!(cpu.run(), StopReason::Breakpoint(x_bp));
+        assert_eq!(cpu.cycles(), 4);
+        cpu.clear_breakpoints();
+        assert_eq!(cpu.run(), StopReason::Halted);
+    }
+
+    #[test]
+    fn test_observer_and_trace() {
+        let isa = InstructionSet::handheld();
+        let mut seen = vec![];
+        let mut record = |s: &CycleState| seen.push(s.cycle);
+        let mut cpu = Cpu::new(&isa, small_program(&isa));
+        cpu.add_observer(&mut record);
+        cpu.enable_trace();
+        assert_eq!(cpu.step_instruction().map(|s| s.cycle), Some(1));
+        assert_eq!(cpu.run_until(|s| s.register(X) == 4), StopReason::Condition);
+        assert_eq!(cpu.state_at(3).unwrap().register(X), 1);
+        assert_eq!(cpu.state_at(4).unwrap().pc, 2);
+        assert!(cpu
+            .dump_trace()
+            .ends_with("cycle    4 | pc    2 | X    4 | addx -5\n"));
+        drop(cpu);
+        assert_eq!(seen, vec![1, 2, 3, 4]);
+    }
+}
@@ -0,0 +1,357 @@
+use std::{error::Error, fmt};
+
+pub const MAX_REGISTERS: usize = 8;
+
+/// Register values, indexed by the register's number
+pub type Registers = [isize; MAX_REGISTERS];
+
+/// The handheld's only register (see InstructionSet::handheld)
+pub const X: usize = 0;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OperandKind {
+    Immediate,
+    Register,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Operand {
+    Immediate(isize),
+    Register(usize),
+}
+
+impl Operand {
+    pub fn value(&self, registers: &Registers) -> isize {
+        match self {
+            Operand::Immediate(value) => *value,
+            Operand::Register(register) => registers[*register],
+        }
+    }
+}
+
+/// Applied to the registers at the end of the instruction's last cycle
+pub type Effect = fn(&mut Registers, &[Operand]);
+
+pub struct InstructionDef {
+    pub mnemonic: String,
+    pub cycles: usize,
+    pub operands: Vec<OperandKind>,
+    pub effect: Effect,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Instruction {
+    /// Index into the InstructionSet's instructions
+    pub opcode: usize,
+    pub operands: Vec<Operand>,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum ParseErrorKind {
+    UnknownInstruction(String),
+    WrongOperandCount { expected: usize, found: usize },
+    BadImmediate(String),
+    UnknownRegister(String),
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct ParseError {
+    /// Starts at 1
+    pub line: usize,
+    pub kind: ParseErrorKind,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}: ", self.line)?;
+        match &self.kind {
+            ParseErrorKind::UnknownInstruction(mnemonic) => {
+                write!(f, "unknown instruction '{mnemonic}'")
+            }
+            ParseErrorKind::WrongOperandCount { expected, found } => {
+                write!(f, "expected {expected} operand(s), found {found}")
+            }
+            ParseErrorKind::BadImmediate(s) => write!(f, "'{s}' is not a number"),
+            ParseErrorKind::UnknownRegister(s) => write!(f, "unknown register '{s}'"),
+        }
+    }
+}
+
+impl Error for ParseError {}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum IsaError {
+    TooManyRegisters,
+    DuplicateRegister(String),
+    DuplicateInstruction(String),
+    /// Every instruction takes at least one cycle
+    ZeroCycles(String),
+}
+
+impl fmt::Display for IsaError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            IsaError::TooManyRegisters => write!(f, "only {MAX_REGISTERS} registers supported"),
+            IsaError::DuplicateRegister(name) => write!(f, "register '{name}' already exists"),
+            IsaError::DuplicateInstruction(mnemonic) => {
+                write!(f, "instruction '{mnemonic}' already exists")
+            }
+            IsaError::ZeroCycles(mnemonic) => {
+                write!(f, "instruction '{mnemonic}' must take at least one cycle")
+            }
+        }
+    }
+}
+
+impl Error for IsaError {}
+
+/// Table of the registers and instructions a CPU understands
+#[derive(Default)]
+pub struct InstructionSet {
+    instructions: Vec<InstructionDef>,
+    /// Name and initial value
+    registers: Vec<(String, isize)>,
+}
+
+impl InstructionSet {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// The handheld device's CPU (day10): X starts at 1, addx V takes 2 cycles, noop takes 1
+    pub fn handheld() -> Self {
+        let mut isa = InstructionSet::new();
+        isa.add_register("X", 1).unwrap();
+        isa.add_instruction(
+            "addx",
+            2,
+            &[OperandKind::Immediate],
+            |registers, operands| {
+                registers[X] += operands[0].value(registers);
+            },
+        )
+        .unwrap();
+        isa.add_instruction("noop", 1, &[], |_, _| {}).unwrap();
+        isa
+    }
+
+    /// Returns the register's number
+    pub fn add_register(&mut self, name: &str, initial: isize) -> Result<usize, IsaError> {
+        if self.register(name).is_some() {
+            return Err(IsaError::DuplicateRegister(name.to_string()));
+        }
+        if self.registers.len() == MAX_REGISTERS {
+            return Err(IsaError::TooManyRegisters);
+        }
+        self.registers.push((name.to_string(), initial));
+        Ok(self.registers.len() - 1)
+    }
+
+    /// Returns the instruction's opcode
+    pub fn add_instruction(
+        &mut self,
+        mnemonic: &str,
+        cycles: usize,
+        operands: &[OperandKind],
+        effect: Effect,
+    ) -> Result<usize, IsaError> {
+        if self.opcode(mnemonic).is_some() {
+            return Err(IsaError::DuplicateInstruction(mnemonic.to_string()));
+        }
+        if cycles == 0 {
+            return Err(IsaError::ZeroCycles(mnemonic.to_string()));
+        }
+        self.instructions.push(InstructionDef {
+            mnemonic: mnemonic.to_string(),
+            cycles,
+            operands: operands.to_vec(),
+            effect,
+        });
+        Ok(self.instructions.len() - 1)
+    }
+
+    pub fn register(&self, name: &str) -> Option<usize> {
+        self.registers.iter().position(|(n, _)| n == name)
+    }
+
+    pub fn register_name(&self, register: usize) -> &str {
+        &self.registers[register].0
+    }
+
+    pub fn num_registers(&self) -> usize {
+        self.registers.len()
+    }
+
+    pub fn initial_registers(&self) -> Registers {
+        let mut registers = [0; MAX_REGISTERS];
+        for (ndx, (_, initial)) in self.registers.iter().enumerate() {
+            registers[ndx] = *initial;
+        }
+        registers
+    }
+
+    pub fn opcode(&self, mnemonic: &str) -> Option<usize> {
+        self.instructions
+            .iter()
+            .position(|i| i.mnemonic == mnemonic)
+    }
+
+    pub fn def(&self, opcode: usize) -> &InstructionDef {
+        &self.instructions[opcode]
+    }
+
+    pub fn cycles(&self, instruction: &Instruction) -> usize {
+        self.def(instruction.opcode).cycles
+    }
+
+    pub fn execute(&self, instruction: &Instruction, registers: &mut Registers) {
+        (self.def(instruction.opcode).effect)(registers, &instruction.operands)
+    }
+
+    /// One instruction per line, blank lines are skipped
+    pub fn parse(&self, source: &str) -> Result<Vec<Instruction>, ParseError> {
+        source
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty())
+            .map(|(ndx, line)| {
+                self.parse_line(line).map_err(|kind| ParseError {
+                    line: ndx + 1,
+                    kind,
+                })
+            })
+            .collect()
+    }
+
+    pub fn parse_line(&self, line: &str) -> Result<Instruction, ParseErrorKind> {
+        let mut words = line.split_whitespace();
+        let mnemonic = words.next().unwrap_or_default();
+        let opcode = self
+            .opcode(mnemonic)
+            .ok_or_else(|| ParseErrorKind::UnknownInstruction(mnemonic.to_string()))?;
+        let def = self.def(opcode);
+        let words: Vec<&str> = words.collect();
+        if words.len() != def.operands.len() {
+            return Err(ParseErrorKind::WrongOperandCount {
+                expected: def.operands.len(),
+                found: words.len(),
+            });
+        }
+        let operands = def
+            .operands
+            .iter()
+            .zip(words)
+            .map(|(kind, word)| match kind {
+                OperandKind::Immediate => word
+                    .parse()
+                    .map(Operand::Immediate)
+                    .map_err(|_| ParseErrorKind::BadImmediate(word.to_string())),
+                OperandKind::Register => self
+                    .register(word)
+                    .map(Operand::Register)
+                    .ok_or_else(|| ParseErrorKind::UnknownRegister(word.to_string())),
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Instruction { opcode, operands })
+    }
+
+    /// The instruction in source form, e.g. "addx -5"
+    pub fn format(&self, instruction: &Instruction) -> String {
+        let mut s = self.def(instruction.opcode).mnemonic.clone();
+        for operand in &instruction.operands {
+            s.push(' ');
+            match operand {
+                Operand::Immediate(value) => s.push_str(&value.to_string()),
+                Operand::Register(register) => s.push_str(self.register_name(*register)),
+            }
+        }
+        s
+    }
+
+    /// One line per instruction, with the cycles it executes during
+    pub fn disassemble(&self, program: &[Instruction]) -> String {
+        let mut listing = String::new();
+        let mut cycle = 1;
+        for (ndx, instruction) in program.iter().enumerate() {
+            let cycles = self.cycles(instruction);
+            let end = cycle + cycles - 1;
+            listing.push_str(&format!(
+                "{ndx:>4} | cycle {cycle:>4}-{end:<4} | {}\n",
+                self.format(instruction)
+            ));
+            cycle += cycles;
+        }
+        listing
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{InstructionSet, IsaError, OperandKind, ParseError, ParseErrorKind, X};
+
+    #[test]
+    fn test_parse() {
+        let isa = InstructionSet::handheld();
+        let program = isa.parse("noop\naddx 3\naddx -5").unwrap();
+        assert_eq!(program.len(), 3);
+        assert_eq!(isa.format(&program[2]), "addx -5");
+        assert_eq!(
+            isa.disassemble(&program),
+            "   0 | cycle    1-1    | noop\n   1 | cycle    2-3    | addx 3\n   2 | cycle    4-5    | addx -5\n"
+        );
+
+        assert_eq!(
+            isa.parse("noop\n\naddx x"),
+            Err(ParseError {
+                line: 3,
+                kind: ParseErrorKind::BadImmediate("x".to_string())
+            })
+        );
+        assert_eq!(
+            isa.parse("jmp 4").unwrap_err().to_string(),
+            "line 1: unknown instruction 'jmp'"
+        );
+        assert_eq!(
+            isa.parse("addx").unwrap_err().kind,
+            ParseErrorKind::WrongOperandCount {
+                expected: 1,
+                found: 0
+            }
+        );
+    }
+
+    #[test]
+    fn test_extend() {
+        let mut isa = InstructionSet::handheld();
+        let y = isa.add_register("Y", 0).unwrap();
+        assert!(isa.add_register("X", 0).is_err());
+        isa.add_instruction(
+            "mov",
+            1,
+            &[OperandKind::Register, OperandKind::Register],
+            |registers, operands| {
+                let value = operands[1].value(registers);
+                if let super::Operand::Register(dest) = operands[0] {
+                    registers[dest] = value;
+                }
+            },
+        )
+        .unwrap();
+        assert_eq!(
+            isa.add_instruction("nop0", 0, &[], |_, _| {}),
+            Err(IsaError::ZeroCycles("nop0".to_string()))
+        );
+
+        let program = isa.parse("addx 4\nmov Y X").unwrap();
+        let mut registers = isa.initial_registers();
+        for instruction in &program {
+            isa.execute(instruction, &mut registers);
+        }
+        assert_eq!(registers[X], 5);
+        assert_eq!(registers[y], 5);
+        assert_eq!(
+            isa.parse("mov Z X").unwrap_err().kind,
+            ParseErrorKind::UnknownRegister("Z".to_string())
+        );
+    }
+}
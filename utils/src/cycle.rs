@@ -0,0 +1,190 @@
+use std::{
+    collections::HashMap,
+    hash::Hash,
+    ops::{Add, Mul, Sub},
+};
+
+/// A repeating section of a simulation, state at step `start + length` == state at step `start`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Cycle {
+    /// First step that is part of the cycle
+    pub start: usize,
+    pub length: usize,
+}
+
+impl Cycle {
+    /// The step (< start + length) that has the same state as step `n`
+    pub fn equivalent_step(&self, n: usize) -> usize {
+        if n < self.start {
+            n
+        } else {
+            self.start + (n - self.start) % self.length
+        }
+    }
+
+    /// Extrapolates a value that grows by the same amount every cycle (a count, a height, ...) to step `n`
+    ///
+    /// `values[i]` is the value at step i, and must cover steps 0..=start + length
+    pub fn extrapolate<V>(&self, values: &[V], n: usize) -> V
+    where
+        V: Copy + Add<Output = V> + Sub<Output = V> + Mul<Output = V> + TryFrom<usize>,
+    {
+        let end = self.start + self.length;
+        assert!(
+            values.len() > end,
+            "Need values up to step {end} to extrapolate"
+        );
+        if n <= end {
+            return values[n];
+        }
+        let per_cycle = values[end] - values[self.start];
+        let num_cycles = V::try_from((n - self.start) / self.length)
+            .unwrap_or_else(|_| panic!("Too many cycles to represent"));
+        values[self.equivalent_step(n)] + per_cycle * num_cycles
+    }
+}
+
+/// Steps the simulation until a state repeats, remembering every state's `hash` in a HashMap
+///
+/// Gives up (None) after `max_steps`
+pub fn find_cycle<S, K, H, F>(initial: S, hash: H, step: F, max_steps: usize) -> Option<Cycle>
+where
+    K: Hash + Eq,
+    H: FnMut(&S) -> K,
+    F: FnMut(&mut S),
+{
+    find_cycle_with_values(initial, hash, step, |_| (), max_steps).map(|(cycle, _)| cycle)
+}
+
+/// Same as find_cycle, also recording `value(state)` for every step 0..=start + length
+///
+/// The values can then be passed to Cycle::extrapolate
+pub fn find_cycle_with_values<S, K, V, H, F, G>(
+    mut state: S,
+    mut hash: H,
+    mut step: F,
+    mut value: G,
+    max_steps: usize,
+) -> Option<(Cycle, Vec<V>)>
+where
+    K: Hash + Eq,
+    H: FnMut(&S) -> K,
+    F: FnMut(&mut S),
+    G: FnMut(&S) -> V,
+{
+    let mut seen: HashMap<K, usize> = HashMap::new();
+    let mut values = vec![];
+    for step_ndx in 0..=max_steps {
+        values.push(value(&state));
+        if let Some(start) = seen.insert(hash(&state), step_ndx) {
+            return Some((
+                Cycle {
+                    start,
+                    length: step_ndx - start,
+                },
+                values,
+            ));
+        }
+        step(&mut state);
+    }
+    None
+}
+
+/// Brent's algorithm, only keeps two states in memory (instead of every state's hash)
+///
+/// Gives up (None) after `max_steps`
+pub fn find_cycle_brent<S, K, H, F>(
+    initial: S,
+    mut hash: H,
+    mut step: F,
+    max_steps: usize,
+) -> Option<Cycle>
+where
+    S: Clone,
+    K: Eq,
+    H: FnMut(&S) -> K,
+    F: FnMut(&mut S),
+{
+    // 1. Find the cycle length, by moving the tortoise to the hare in growing powers of two
+    let mut power = 1;
+    let mut length = 1;
+    let mut steps = 0;
+    let mut tortoise = hash(&initial);
+    let mut hare_state = initial.clone();
+    step(&mut hare_state);
+    let mut hare = hash(&hare_state);
+    while tortoise != hare {
+        if power == length {
+            tortoise = hare;
+            power *= 2;
+            length = 0;
+        }
+        step(&mut hare_state);
+        hare = hash(&hare_state);
+        length += 1;
+        steps += 1;
+        if steps > max_steps {
+            return None;
+        }
+    }
+
+    // 2. Find the start, with two states `length` steps apart
+    let mut tortoise_state = initial.clone();
+    let mut hare_state = initial;
+    for _ in 0..length {
+        step(&mut hare_state);
+    }
+    let mut start = 0;
+    while hash(&tortoise_state) != hash(&hare_state) {
+        step(&mut tortoise_state);
+        step(&mut hare_state);
+        start += 1;
+    }
+    Some(Cycle { start, length })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{find_cycle, find_cycle_brent, find_cycle_with_values, Cycle};
+
+    // 0, 1, 2, 3, 4, 5, 2, 3, 4, 5, 2, ...
+    fn step(n: &mut usize) {
+        *n = if *n == 5 { 2 } else { *n + 1 };
+    }
+
+    #[test]
+    fn test_find_cycle() {
+        let expected = Cycle {
+            start: 2,
+            length: 4,
+        };
+        assert_eq!(find_cycle(0_usize, |&n| n, step, 100), Some(expected));
+        assert_eq!(find_cycle_brent(0_usize, |&n| n, step, 100), Some(expected));
+        assert_eq!(find_cycle(0_usize, |&n| n, |n| *n += 1, 100), None);
+        assert_eq!(find_cycle_brent(0_usize, |&n| n, |n| *n += 1, 100), None);
+    }
+
+    #[test]
+    fn test_extrapolate() {
+        // Track a running total alongside the state
+        let (cycle, values) = find_cycle_with_values(
+            (0_usize, 0_usize),
+            |&(n, _)| n,
+            |(n, total)| {
+                step(n);
+                *total += *n;
+            },
+            |&(_, total)| total,
+            100,
+        )
+        .unwrap();
+
+        let mut brute = (0_usize, 0_usize);
+        for _ in 0..1000 {
+            step(&mut brute.0);
+            brute.1 += brute.0;
+        }
+        assert_eq!(cycle.extrapolate(&values, 1000), brute.1);
+        assert_eq!(cycle.equivalent_step(1000), 2 + (998 % 4));
+    }
+}
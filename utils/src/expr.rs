@@ -0,0 +1,449 @@
+use std::{error::Error, fmt};
+
+use num_bigint::BigUint;
+
+/// Numbers an expression can be evaluated over
+pub trait Arithmetic: Sized {
+    fn constant(value: u64) -> Result<Self, EvalError>;
+    fn add(&self, rhs: &Self) -> Result<Self, EvalError>;
+    fn sub(&self, rhs: &Self) -> Result<Self, EvalError>;
+    fn mul(&self, rhs: &Self) -> Result<Self, EvalError>;
+    fn div(&self, rhs: &Self) -> Result<Self, EvalError>;
+    fn rem(&self, rhs: &Self) -> Result<Self, EvalError>;
+}
+
+// Primitive integers use checked math, so overflow is reported instead of wrapping
+macro_rules! checked_arithmetic {
+    ($($int:ty),*) => {
+        $(
+            impl Arithmetic for $int {
+                fn constant(value: u64) -> Result<Self, EvalError> {
+                    <$int>::try_from(value).map_err(|_| EvalError::Overflow)
+                }
+
+                fn add(&self, rhs: &Self) -> Result<Self, EvalError> {
+                    self.checked_add(*rhs).ok_or(EvalError::Overflow)
+                }
+
+                fn sub(&self, rhs: &Self) -> Result<Self, EvalError> {
+                    self.checked_sub(*rhs).ok_or(EvalError::Overflow)
+                }
+
+                fn mul(&self, rhs: &Self) -> Result<Self, EvalError> {
+                    self.checked_mul(*rhs).ok_or(EvalError::Overflow)
+                }
+
+                fn div(&self, rhs: &Self) -> Result<Self, EvalError> {
+                    if *rhs == 0 {
+                        return Err(EvalError::DivideByZero);
+                    }
+                    self.checked_div(*rhs).ok_or(EvalError::Overflow)
+                }
+
+                fn rem(&self, rhs: &Self) -> Result<Self, EvalError> {
+                    if *rhs == 0 {
+                        return Err(EvalError::DivideByZero);
+                    }
+                    self.checked_rem(*rhs).ok_or(EvalError::Overflow)
+                }
+            }
+        )*
+    };
+}
+
+checked_arithmetic!(u32, u64, u128, usize, i64, i128, isize);
+
+// Can't overflow, only go negative
+impl Arithmetic for BigUint {
+    fn constant(value: u64) -> Result<Self, EvalError> {
+        Ok(BigUint::from(value))
+    }
+
+    fn add(&self, rhs: &Self) -> Result<Self, EvalError> {
+        Ok(self + rhs)
+    }
+
+    fn sub(&self, rhs: &Self) -> Result<Self, EvalError> {
+        if rhs > self {
+            return Err(EvalError::Overflow);
+        }
+        Ok(self - rhs)
+    }
+
+    fn mul(&self, rhs: &Self) -> Result<Self, EvalError> {
+        Ok(self * rhs)
+    }
+
+    fn div(&self, rhs: &Self) -> Result<Self, EvalError> {
+        if *rhs == BigUint::ZERO {
+            return Err(EvalError::DivideByZero);
+        }
+        Ok(self / rhs)
+    }
+
+    fn rem(&self, rhs: &Self) -> Result<Self, EvalError> {
+        if *rhs == BigUint::ZERO {
+            return Err(EvalError::DivideByZero);
+        }
+        Ok(self % rhs)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BinOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Rem,
+}
+
+impl BinOp {
+    fn precedence(&self) -> u8 {
+        match self {
+            BinOp::Add | BinOp::Sub => 1,
+            BinOp::Mul | BinOp::Div | BinOp::Rem => 2,
+        }
+    }
+
+    fn apply<T: Arithmetic>(&self, lhs: &T, rhs: &T) -> Result<T, EvalError> {
+        match self {
+            BinOp::Add => lhs.add(rhs),
+            BinOp::Sub => lhs.sub(rhs),
+            BinOp::Mul => lhs.mul(rhs),
+            BinOp::Div => lhs.div(rhs),
+            BinOp::Rem => lhs.rem(rhs),
+        }
+    }
+}
+
+impl fmt::Display for BinOp {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let c = match self {
+            BinOp::Add => '+',
+            BinOp::Sub => '-',
+            BinOp::Mul => '*',
+            BinOp::Div => '/',
+            BinOp::Rem => '%',
+        };
+        write!(f, "{c}")
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Expr {
+    Constant(u64),
+    Var(String),
+    BinOp(Box<Expr>, BinOp, Box<Expr>),
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum ParseError {
+    /// Byte offset of the unexpected char
+    UnexpectedChar {
+        offset: usize,
+        found: char,
+    },
+    UnexpectedEnd,
+    ConstantTooLarge {
+        offset: usize,
+    },
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParseError::UnexpectedChar { offset, found } => {
+                write!(f, "unexpected '{found}' at offset {offset}")
+            }
+            ParseError::UnexpectedEnd => write!(f, "unexpected end of expression"),
+            ParseError::ConstantTooLarge { offset } => {
+                write!(f, "constant at offset {offset} is too large")
+            }
+        }
+    }
+}
+
+impl Error for ParseError {}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum EvalError {
+    UnknownVariable(String),
+    Overflow,
+    DivideByZero,
+    /// The number type can't do this operation (e.g. division of residues)
+    Unsupported(BinOp),
+}
+
+impl fmt::Display for EvalError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            EvalError::UnknownVariable(name) => write!(f, "unknown variable '{name}'"),
+            EvalError::Overflow => write!(f, "overflow"),
+            EvalError::DivideByZero => write!(f, "divide by zero"),
+            EvalError::Unsupported(op) => write!(f, "'{op}' is not supported"),
+        }
+    }
+}
+
+impl Error for EvalError {}
+
+/// Recursive descent over:
+///  expr   = term (('+' | '-') term)*
+///  term   = factor (('*' | '/' | '%') factor)*
+///  factor = number | name | '(' expr ')'
+struct Parser<'a> {
+    s: &'a str,
+    offset: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn skip_whitespace(&mut self) {
+        let rest = &self.s[self.offset..];
+        self.offset += rest.len() - rest.trim_start().len();
+    }
+
+    fn peek(&mut self) -> Option<char> {
+        self.skip_whitespace();
+        self.s[self.offset..].chars().next()
+    }
+
+    fn unexpected(&self, found: char) -> ParseError {
+        ParseError::UnexpectedChar {
+            offset: self.offset,
+            found,
+        }
+    }
+
+    fn take_while(&mut self, f: impl Fn(char) -> bool) -> &'a str {
+        let start = self.offset;
+        let len = self.s[start..]
+            .find(|c| !f(c))
+            .unwrap_or(self.s.len() - start);
+        self.offset += len;
+        &self.s[start..self.offset]
+    }
+
+    fn expr(&mut self) -> Result<Expr, ParseError> {
+        let mut lhs = self.term()?;
+        while let Some(op) = match self.peek() {
+            Some('+') => Some(BinOp::Add),
+            Some('-') => Some(BinOp::Sub),
+            _ => None,
+        } {
+            self.offset += 1;
+            lhs = Expr::BinOp(Box::new(lhs), op, Box::new(self.term()?));
+        }
+        Ok(lhs)
+    }
+
+    fn term(&mut self) -> Result<Expr, ParseError> {
+        let mut lhs = self.factor()?;
+        while let Some(op) = match self.peek() {
+            Some('*') => Some(BinOp::Mul),
+            Some('/') => Some(BinOp::Div),
+            Some('%') => Some(BinOp::Rem),
+            _ => None,
+        } {
+            self.offset += 1;
+            lhs = Expr::BinOp(Box::new(lhs), op, Box::new(self.factor()?));
+        }
+        Ok(lhs)
+    }
+
+    fn factor(&mut self) -> Result<Expr, ParseError> {
+        match self.peek() {
+            None => Err(ParseError::UnexpectedEnd),
+            Some('(') => {
+                self.offset += 1;
+                let inner = self.expr()?;
+                match self.peek() {
+                    Some(')') => {
+                        self.offset += 1;
+                        Ok(inner)
+                    }
+                    Some(c) => Err(self.unexpected(c)),
+                    None => Err(ParseError::UnexpectedEnd),
+                }
+            }
+            Some(c) if c.is_ascii_digit() => {
+                let offset = self.offset;
+                self.take_while(|c| c.is_ascii_digit())
+                    .parse()
+                    .map(Expr::Constant)
+                    .map_err(|_| ParseError::ConstantTooLarge { offset })
+            }
+            Some(c) if c.is_alphabetic() || c == '_' => Ok(Expr::Var(
+                self.take_while(|c| c.is_alphanumeric() || c == '_')
+                    .to_string(),
+            )),
+            Some(c) => Err(self.unexpected(c)),
+        }
+    }
+}
+
+impl Expr {
+    pub fn parse(s: &str) -> Result<Expr, ParseError> {
+        let mut parser = Parser { s, offset: 0 };
+        let expr = parser.expr()?;
+        match parser.peek() {
+            None => Ok(expr),
+            Some(c) => Err(parser.unexpected(c)),
+        }
+    }
+
+    /// Evaluates the expression, looking up variables with `lookup`
+    pub fn eval<T, F>(&self, lookup: &F) -> Result<T, EvalError>
+    where
+        T: Arithmetic,
+        F: Fn(&str) -> Option<T>,
+    {
+        match self {
+            Expr::Constant(value) => T::constant(*value),
+            Expr::Var(name) => lookup(name).ok_or_else(|| EvalError::UnknownVariable(name.clone())),
+            Expr::BinOp(lhs, op, rhs) => op.apply(&lhs.eval(lookup)?, &rhs.eval(lookup)?),
+        }
+    }
+
+    /// Pre-computes every sub-expression without variables, and drops `+ 0`, `* 1` and friends
+    pub fn fold(self) -> Expr {
+        let Expr::BinOp(lhs, op, rhs) = self else {
+            return self;
+        };
+        let (lhs, rhs) = (lhs.fold(), rhs.fold());
+        match (&lhs, op, &rhs) {
+            (Expr::Constant(a), op, Expr::Constant(b)) => match op.apply(a, b) {
+                Ok(value) => Expr::Constant(value),
+                // Leave it for eval to report
+                Err(_) => Expr::BinOp(Box::new(lhs), op, Box::new(rhs)),
+            },
+            (e, BinOp::Add | BinOp::Sub, Expr::Constant(0))
+            | (e, BinOp::Mul | BinOp::Div, Expr::Constant(1))
+            | (Expr::Constant(0), BinOp::Add, e)
+            | (Expr::Constant(1), BinOp::Mul, e) => e.clone(),
+            _ => Expr::BinOp(Box::new(lhs), op, Box::new(rhs)),
+        }
+    }
+
+    /// Names of the variables the expression uses
+    pub fn variables(&self) -> Vec<&str> {
+        match self {
+            Expr::Constant(_) => vec![],
+            Expr::Var(name) => vec![name.as_str()],
+            Expr::BinOp(lhs, _, rhs) => {
+                let mut vars = lhs.variables();
+                for v in rhs.variables() {
+                    if !vars.contains(&v) {
+                        vars.push(v);
+                    }
+                }
+                vars
+            }
+        }
+    }
+
+    // Writes the sub-expression, adding parentheses if it binds looser than its parent
+    fn fmt_child(&self, f: &mut fmt::Formatter, parent: u8, is_rhs: bool) -> fmt::Result {
+        match self {
+            Expr::BinOp(_, op, _)
+                if op.precedence() < parent || (is_rhs && op.precedence() == parent) =>
+            {
+                write!(f, "({self})")
+            }
+            _ => write!(f, "{self}"),
+        }
+    }
+}
+
+impl fmt::Display for Expr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Expr::Constant(value) => write!(f, "{value}"),
+            Expr::Var(name) => write!(f, "{name}"),
+            Expr::BinOp(lhs, op, rhs) => {
+                lhs.fmt_child(f, op.precedence(), false)?;
+                write!(f, " {op} ")?;
+                rhs.fmt_child(f, op.precedence(), true)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use num_bigint::BigUint;
+
+    use super::{EvalError, Expr, ParseError};
+
+    fn eval_old(expr: &Expr, old: u64) -> Result<u64, EvalError> {
+        expr.eval(&|name| (name == "old").then_some(old))
+    }
+
+    #[test]
+    fn test_parse_eval() {
+        let expr = Expr::parse("old * old").unwrap();
+        assert_eq!(eval_old(&expr, 7), Ok(49));
+
+        let expr = Expr::parse("(old + 3) * 2 - old / 2 % 3").unwrap();
+        assert_eq!(eval_old(&expr, 10), Ok(26 - 2));
+        assert_eq!(expr.to_string(), "(old + 3) * 2 - old / 2 % 3");
+        assert_eq!(
+            Expr::parse("old - (1 - 2)").unwrap().to_string(),
+            "old - (1 - 2)"
+        );
+
+        assert_eq!(
+            eval_old(&Expr::parse("old / 0").unwrap(), 1),
+            Err(EvalError::DivideByZero)
+        );
+        assert_eq!(
+            eval_old(&Expr::parse("old - 2").unwrap(), 1),
+            Err(EvalError::Overflow)
+        );
+        assert_eq!(
+            Expr::parse("old * old * old")
+                .unwrap()
+                .eval(&|_| Some(u64::MAX / 2)),
+            Err(EvalError::Overflow)
+        );
+        let big: BigUint = Expr::parse("old * old * old")
+            .unwrap()
+            .eval(&|_| Some(BigUint::from(u64::MAX)))
+            .unwrap();
+        assert_eq!(big, BigUint::from(u64::MAX).pow(3));
+        assert_eq!(
+            eval_old(&Expr::parse("new + 1").unwrap(), 1),
+            Err(EvalError::UnknownVariable("new".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert_eq!(
+            Expr::parse("old * $"),
+            Err(ParseError::UnexpectedChar {
+                offset: 6,
+                found: '$'
+            })
+        );
+        assert_eq!(Expr::parse("(old + 1"), Err(ParseError::UnexpectedEnd));
+        assert_eq!(
+            Expr::parse("old 1"),
+            Err(ParseError::UnexpectedChar {
+                offset: 4,
+                found: '1'
+            })
+        );
+    }
+
+    #[test]
+    fn test_fold() {
+        let expr = Expr::parse("old * (2 * 3 + 1) + 0").unwrap().fold();
+        assert_eq!(expr.to_string(), "old * 7");
+        assert_eq!(
+            Expr::parse("1 * (old)").unwrap().fold(),
+            Expr::Var("old".to_string())
+        );
+        assert_eq!(expr.variables(), vec!["old"]);
+    }
+}
@@ -0,0 +1,274 @@
+use std::ops::RangeInclusive;
+
+use crate::Point2D;
+
+pub fn manhattan_distance(a: Point2D<isize>, b: Point2D<isize>) -> isize {
+    (a.x - b.x).abs() + (a.y - b.y).abs()
+}
+
+// Rotate 45 degrees so the diamond edges become axis-aligned lines
+//  u = x + y (edges running NE-SW)
+//  v = x - y (edges running NW-SE)
+pub fn rotate(p: Point2D<isize>) -> (isize, isize) {
+    (p.x + p.y, p.x - p.y)
+}
+
+/// Inverse of rotate, only lattice points are returned (u and v must share parity)
+pub fn unrotate(u: isize, v: isize) -> Option<Point2D<isize>> {
+    if (u + v).rem_euclid(2) != 0 {
+        return None;
+    }
+    Some(Point2D {
+        x: (u + v) / 2,
+        y: (u - v) / 2,
+    })
+}
+
+/// All points within `radius` (manhattan) of `center`
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Diamond {
+    pub center: Point2D<isize>,
+    pub radius: isize,
+}
+
+impl Diamond {
+    pub fn new(center: Point2D<isize>, radius: isize) -> Self {
+        Diamond { center, radius }
+    }
+
+    /// A diamond centered at `center` that just reaches `edge`
+    pub fn from_edge(center: Point2D<isize>, edge: Point2D<isize>) -> Self {
+        Diamond {
+            center,
+            radius: manhattan_distance(center, edge),
+        }
+    }
+
+    /// Same center, radius changed by `amount`
+    pub fn grow(&self, amount: isize) -> Self {
+        Diamond {
+            center: self.center,
+            radius: self.radius + amount,
+        }
+    }
+
+    pub fn contains(&self, p: Point2D<isize>) -> bool {
+        manhattan_distance(self.center, p) <= self.radius
+    }
+
+    /// The (inclusive) x values covered on row y, if any
+    pub fn row_interval(&self, y: isize) -> Option<RangeInclusive<isize>> {
+        let half_width = self.radius - (self.center.y - y).abs();
+        if half_width < 0 {
+            None
+        } else {
+            Some(self.center.x - half_width..=self.center.x + half_width)
+        }
+    }
+
+    /// The rotated coordinates of the four edges: ([u_min, u_max], [v_min, v_max])
+    pub fn boundary_lines(&self) -> ([isize; 2], [isize; 2]) {
+        let (u, v) = rotate(self.center);
+        ([u - self.radius, u + self.radius], [v - self.radius, v + self.radius])
+    }
+
+    /// Iterates every point exactly `radius` away, clockwise from the top
+    pub fn perimeter(&self) -> PerimeterIter {
+        PerimeterIter {
+            diamond: *self,
+            ndx: 0,
+        }
+    }
+}
+
+pub struct PerimeterIter {
+    diamond: Diamond,
+    ndx: isize,
+}
+
+impl Iterator for PerimeterIter {
+    type Item = Point2D<isize>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let Diamond { center, radius } = self.diamond;
+        if radius < 0 {
+            return None;
+        }
+        if radius == 0 {
+            // A single point
+            self.ndx += 1;
+            return if self.ndx == 1 { Some(center) } else { None };
+        }
+        if self.ndx >= 4 * radius {
+            return None;
+        }
+        let (side, k) = (self.ndx / radius, self.ndx % radius);
+        self.ndx += 1;
+        let (x, y) = match side {
+            // Top -> Right
+            0 => (center.x + k, center.y - radius + k),
+            // Right -> Bottom
+            1 => (center.x + radius - k, center.y + k),
+            // Bottom -> Left
+            2 => (center.x - k, center.y + radius - k),
+            // Left -> Top
+            _ => (center.x - radius + k, center.y - k),
+        };
+        Some(Point2D { x, y })
+    }
+}
+
+/// Sorts the ranges and joins any that overlap or touch
+pub fn merge_intervals(mut ranges: Vec<RangeInclusive<isize>>) -> Vec<RangeInclusive<isize>> {
+    ranges.retain(|r| !r.is_empty());
+    ranges.sort_unstable_by_key(|r| *r.start());
+    let mut merged: Vec<RangeInclusive<isize>> = Vec::with_capacity(ranges.len());
+    for r in ranges {
+        match merged.last_mut() {
+            Some(last) if *r.start() <= last.end() + 1 => {
+                if r.end() > last.end() {
+                    *last = *last.start()..=*r.end();
+                }
+            }
+            _ => merged.push(r),
+        }
+    }
+    merged
+}
+
+/// The parts of `within` not covered by `merged` (as returned by merge_intervals)
+pub fn interval_gaps(
+    merged: &[RangeInclusive<isize>],
+    within: RangeInclusive<isize>,
+) -> Vec<RangeInclusive<isize>> {
+    let mut gaps = vec![];
+    let mut next = *within.start();
+    for r in merged {
+        if next > *within.end() {
+            break;
+        }
+        if *r.start() > next {
+            gaps.push(next..=(*r.start() - 1).min(*within.end()));
+        }
+        next = next.max(r.end() + 1);
+    }
+    if next <= *within.end() {
+        gaps.push(next..=*within.end());
+    }
+    gaps
+}
+
+/// Finds a point inside the box [min, max] that isn't covered by any of the diamonds
+///
+/// If a single point is uncovered, it must sit on the lines just outside the diamonds'
+/// edges (or against the box), so only the intersections of those lines are checked.
+pub fn find_uncovered(
+    diamonds: &[Diamond],
+    min: Point2D<isize>,
+    max: Point2D<isize>,
+) -> Option<Point2D<isize>> {
+    let mut u_lines = vec![];
+    let mut v_lines = vec![];
+    for d in diamonds {
+        let ([u_min, u_max], [v_min, v_max]) = d.grow(1).boundary_lines();
+        u_lines.extend([u_min, u_max]);
+        v_lines.extend([v_min, v_max]);
+    }
+    u_lines.sort_unstable();
+    u_lines.dedup();
+    v_lines.sort_unstable();
+    v_lines.dedup();
+
+    let in_box = |p: &Point2D<isize>| p.x >= min.x && p.x <= max.x && p.y >= min.y && p.y <= max.y;
+    let uncovered = |p: &Point2D<isize>| !diamonds.iter().any(|d| d.contains(*p));
+
+    let mut candidates = vec![
+        Point2D { x: min.x, y: min.y },
+        Point2D { x: max.x, y: min.y },
+        Point2D { x: min.x, y: max.y },
+        Point2D { x: max.x, y: max.y },
+    ];
+    // Where the lines meet the box edges
+    for &u in &u_lines {
+        candidates.extend([
+            Point2D { x: min.x, y: u - min.x },
+            Point2D { x: max.x, y: u - max.x },
+            Point2D { x: u - min.y, y: min.y },
+            Point2D { x: u - max.y, y: max.y },
+        ]);
+    }
+    for &v in &v_lines {
+        candidates.extend([
+            Point2D { x: min.x, y: min.x - v },
+            Point2D { x: max.x, y: max.x - v },
+            Point2D { x: min.y + v, y: min.y },
+            Point2D { x: max.y + v, y: max.y },
+        ]);
+    }
+    if let Some(p) = candidates.into_iter().filter(in_box).find(uncovered) {
+        return Some(p);
+    }
+
+    // Where the lines meet each other
+    u_lines
+        .iter()
+        .flat_map(|&u| v_lines.iter().filter_map(move |&v| unrotate(u, v)))
+        .filter(in_box)
+        .find(uncovered)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Point2D;
+
+    use super::{find_uncovered, interval_gaps, merge_intervals, Diamond};
+
+    #[test]
+    fn test_row_interval() {
+        let d = Diamond::new(Point2D { x: 8, y: 7 }, 9);
+        assert_eq!(d.row_interval(7), Some(-1..=17));
+        assert_eq!(d.row_interval(10), Some(2..=14));
+        assert_eq!(d.row_interval(16), Some(8..=8));
+        assert_eq!(d.row_interval(17), None);
+    }
+
+    #[test]
+    fn test_intervals() {
+        let merged = merge_intervals(vec![12..=12, 2..=4, -2..=2, 5..=6, 14..=20, 16..=18]);
+        assert_eq!(merged, vec![-2..=6, 12..=12, 14..=20]);
+        assert_eq!(interval_gaps(&merged, 0..=25), vec![7..=11, 13..=13, 21..=25]);
+        assert_eq!(interval_gaps(&merged, -5..=3), vec![-5..=-3]);
+        assert_eq!(interval_gaps(&merged, 0..=6), vec![]);
+        assert_eq!(interval_gaps(&[], 1..=2), vec![1..=2]);
+    }
+
+    #[test]
+    fn test_perimeter() {
+        let d = Diamond::new(Point2D { x: 0, y: 0 }, 2);
+        let points: Vec<Point2D<isize>> = d.perimeter().collect();
+        assert_eq!(points.len(), 8);
+        assert!(points.iter().all(|p| p.x.abs() + p.y.abs() == 2));
+        assert_eq!(points[0], Point2D { x: 0, y: -2 });
+
+        let d = Diamond::new(Point2D { x: 3, y: 3 }, 0);
+        assert_eq!(d.perimeter().collect::<Vec<_>>(), vec![Point2D { x: 3, y: 3 }]);
+    }
+
+    #[test]
+    fn test_find_uncovered() {
+        // Four diamonds boxing in (5, 5)
+        let diamonds = [
+            Diamond::new(Point2D { x: 2, y: 2 }, 5),
+            Diamond::new(Point2D { x: 8, y: 2 }, 5),
+            Diamond::new(Point2D { x: 2, y: 8 }, 5),
+            Diamond::new(Point2D { x: 8, y: 8 }, 5),
+        ];
+        let p = find_uncovered(&diamonds, Point2D { x: 0, y: 0 }, Point2D { x: 10, y: 10 });
+        assert_eq!(p, Some(Point2D { x: 5, y: 5 }));
+
+        // Uncovered corner of the box
+        let diamonds = [Diamond::new(Point2D { x: 0, y: 0 }, 3)];
+        let p = find_uncovered(&diamonds, Point2D { x: 0, y: 0 }, Point2D { x: 2, y: 2 });
+        assert_eq!(p, Some(Point2D { x: 2, y: 2 }));
+    }
+}
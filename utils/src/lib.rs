@@ -1,5 +1,15 @@
 #![allow(dead_code)]
 
+pub mod bitset;
+pub mod cpu;
+pub mod cycle;
+pub mod expr;
+pub mod geom;
+pub mod memo;
+pub mod ocr;
+pub mod region;
+pub mod residue;
+
 use std::{
     cmp::Ordering,
     fmt::{self, Display},
@@ -146,7 +156,7 @@ where
     type Item = T;
 
     fn get_cell(&self, x: isize, y: isize) -> Option<&Self::Item> {
-        if x >= self.num_cols as isize || y >= self.num_rows as isize {
+        if x < 0 || y < 0 || x >= self.num_cols as isize || y >= self.num_rows as isize {
             None
         } else {
             self.cells.get((y as usize * self.num_cols) + x as usize)
@@ -154,8 +164,12 @@ where
     }
 
     fn get_cell_mut(&mut self, x: isize, y: isize) -> Option<&mut Self::Item> {
-        self.cells
-            .get_mut((y as usize * self.num_cols) + x as usize)
+        if x < 0 || y < 0 || x >= self.num_cols as isize || y >= self.num_rows as isize {
+            None
+        } else {
+            self.cells
+                .get_mut((y as usize * self.num_cols) + x as usize)
+        }
     }
 
     fn first_cell_coord(&self) -> (isize, isize) {
@@ -621,7 +635,7 @@ where
     }
 }
 
-#[derive(Clone, Default, Debug)]
+#[derive(Clone, Copy, Default, Debug, PartialEq, Eq, Hash)]
 pub struct Point2D<CoordType> {
     pub x: CoordType,
     pub y: CoordType,
@@ -637,7 +651,7 @@ pub struct Point {
 mod tests {
     use std::fmt::Display;
 
-    use crate::{DynamicGrid, StaticGrid, Growable};
+    use crate::{DynamicGrid, Grid, Growable, StaticGrid};
 
     /*
     Test Structs
@@ -675,6 +689,20 @@ mod tests {
         assert_eq!(i.next().unwrap().value, 'a');
     }
 
+    #[test]
+    fn test_grid_bounds() {
+        let mut g: StaticGrid<TestCell> = StaticGrid::new(2, 2);
+        g.get_cell_mut(1, 0).unwrap().value = 'a';
+        // Used to wrap around to the end of the previous row
+        assert!(g.get_cell(-1, 1).is_none());
+        assert!(g.get_cell(0, -1).is_none());
+        assert!(g.get_cell(2, 0).is_none());
+        assert!(g.get_cell_mut(-1, 1).is_none());
+        assert!(g.get_cell_mut(1, -1).is_none());
+        assert!(g.get_cell_mut(0, 2).is_none());
+        assert_eq!(g.get_cell(1, 0).unwrap().value, 'a');
+    }
+
     #[test]
     fn test_dynamic_grid() {
         let mut g = DynamicGrid::<TestCell>::new(500, 0);
@@ -0,0 +1,125 @@
+use std::{collections::HashMap, hash::Hash};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct MemoStats {
+    pub hits: usize,
+    pub misses: usize,
+}
+
+/// Caches the results of a recursive function
+///
+/// The function is handed the Memo so it can recurse through the cache:
+///
+/// ```
+/// use utils::memo::Memo;
+///
+/// let fib = |memo: &mut Memo<u64, u64>, n: u64| {
+///     if n < 2 { n } else { memo.get(n - 1) + memo.get(n - 2) }
+/// };
+/// let mut memo = Memo::new(&fib);
+/// assert_eq!(memo.get(80), 23416728348467685);
+/// ```
+pub struct Memo<'f, K, V> {
+    cache: HashMap<K, V>,
+    func: &'f dyn Fn(&mut Memo<'f, K, V>, K) -> V,
+    stats: MemoStats,
+}
+
+impl<'f, K, V> Memo<'f, K, V>
+where
+    K: Hash + Eq + Clone,
+    V: Clone,
+{
+    pub fn new(func: &'f dyn Fn(&mut Memo<'f, K, V>, K) -> V) -> Self {
+        Memo {
+            cache: HashMap::new(),
+            func,
+            stats: MemoStats::default(),
+        }
+    }
+
+    /// Returns the cached result for `key`, calling the function on a miss
+    pub fn get(&mut self, key: K) -> V {
+        if let Some(value) = self.cache.get(&key) {
+            self.stats.hits += 1;
+            return value.clone();
+        }
+        self.stats.misses += 1;
+        // Copy the reference out, so the function can borrow self mutably
+        let func = self.func;
+        let value = func(self, key.clone());
+        self.cache.insert(key, value.clone());
+        value
+    }
+
+    /// The cached result for `key`, without calling the function
+    pub fn peek(&self, key: &K) -> Option<&V> {
+        self.cache.get(key)
+    }
+
+    pub fn stats(&self) -> MemoStats {
+        self.stats
+    }
+
+    pub fn len(&self) -> usize {
+        self.cache.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cache.is_empty()
+    }
+
+    /// Forgets every cached result and resets the stats
+    pub fn clear(&mut self) {
+        self.cache.clear();
+        self.stats = MemoStats::default();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Memo, MemoStats};
+
+    #[test]
+    fn test_memo() {
+        let fib = |memo: &mut Memo<u64, u64>, n: u64| {
+            if n < 2 {
+                n
+            } else {
+                memo.get(n - 1) + memo.get(n - 2)
+            }
+        };
+        let mut memo = Memo::new(&fib);
+        assert_eq!(memo.get(10), 55);
+        // Every value computed once, every n - 2 call (but the last two) a hit
+        assert_eq!(
+            memo.stats(),
+            MemoStats {
+                hits: 8,
+                misses: 11
+            }
+        );
+        assert_eq!(memo.len(), 11);
+
+        assert_eq!(memo.get(10), 55);
+        assert_eq!(memo.stats().hits, 9);
+        assert_eq!(memo.peek(&5), Some(&5));
+    }
+
+    #[test]
+    fn test_memo_captures() {
+        // Number of paths through a grid, around blocked cells
+        let blocked = [(1, 1), (2, 0)];
+        let paths = |memo: &mut Memo<(usize, usize), usize>, (x, y): (usize, usize)| {
+            if blocked.contains(&(x, y)) {
+                0
+            } else if x == 0 || y == 0 {
+                1
+            } else {
+                memo.get((x - 1, y)) + memo.get((x, y - 1))
+            }
+        };
+        let mut memo = Memo::new(&paths);
+        assert_eq!(memo.get((2, 2)), 1);
+    }
+}
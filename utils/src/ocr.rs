@@ -0,0 +1,235 @@
+use std::{error::Error, fmt};
+
+use crate::Grid;
+
+// The block letters the elves' devices draw, 4x6 apart from the 5 wide Y (with 1 blank
+// column between letters)
+const SMALL_FONT: [(char, [&str; 6]); 18] = [
+    ('A', [".##.", "#..#", "#..#", "####", "#..#", "#..#"]),
+    ('B', ["###.", "#..#", "###.", "#..#", "#..#", "###."]),
+    ('C', [".##.", "#..#", "#...", "#...", "#..#", ".##."]),
+    ('E', ["####", "#...", "###.", "#...", "#...", "####"]),
+    ('F', ["####", "#...", "###.", "#...", "#...", "#..."]),
+    ('G', [".##.", "#..#", "#...", "#.##", "#..#", ".###"]),
+    ('H', ["#..#", "#..#", "####", "#..#", "#..#", "#..#"]),
+    ('I', [".###", "..#.", "..#.", "..#.", "..#.", ".###"]),
+    ('J', ["..##", "...#", "...#", "...#", "#..#", ".##."]),
+    ('K', ["#..#", "#.#.", "##..", "#.#.", "#.#.", "#..#"]),
+    ('L', ["#...", "#...", "#...", "#...", "#...", "####"]),
+    ('O', [".##.", "#..#", "#..#", "#..#", "#..#", ".##."]),
+    ('P', ["###.", "#..#", "#..#", "###.", "#...", "#..."]),
+    ('R', ["###.", "#..#", "#..#", "###.", "#.#.", "#..#"]),
+    ('S', [".###", "#...", "#...", ".##.", "...#", "###."]),
+    ('U', ["#..#", "#..#", "#..#", "#..#", "#..#", ".##."]),
+    ('Y', ["#...#", "#...#", ".#.#.", "..#..", "..#..", "..#.."]),
+    ('Z', ["####", "...#", "..#.", ".#..", "#...", "####"]),
+];
+
+// The larger 6x10 letters (with 2 blank columns between letters)
+#[rustfmt::skip]
+const LARGE_FONT: [(char, [&str; 10]); 15] = [
+    ('A', ["..##..", ".#..#.", "#....#", "#....#", "#....#", "######", "#....#", "#....#", "#....#", "#....#"]),
+    ('B', ["#####.", "#....#", "#....#", "#....#", "#####.", "#....#", "#....#", "#....#", "#....#", "#####."]),
+    ('C', [".####.", "#....#", "#.....", "#.....", "#.....", "#.....", "#.....", "#.....", "#....#", ".####."]),
+    ('E', ["######", "#.....", "#.....", "#.....", "#####.", "#.....", "#.....", "#.....", "#.....", "######"]),
+    ('F', ["######", "#.....", "#.....", "#.....", "#####.", "#.....", "#.....", "#.....", "#.....", "#....."]),
+    ('G', [".####.", "#....#", "#.....", "#.....", "#.....", "#..###", "#....#", "#....#", "#...##", ".###.#"]),
+    ('H', ["#....#", "#....#", "#....#", "#....#", "######", "#....#", "#....#", "#....#", "#....#", "#....#"]),
+    ('J', ["...###", "....#.", "....#.", "....#.", "....#.", "....#.", "....#.", "#...#.", "#...#.", ".###.."]),
+    ('K', ["#....#", "#...#.", "#..#..", "#.#...", "##....", "##....", "#.#...", "#..#..", "#...#.", "#....#"]),
+    ('L', ["#.....", "#.....", "#.....", "#.....", "#.....", "#.....", "#.....", "#.....", "#.....", "######"]),
+    ('N', ["#....#", "##...#", "##...#", "#.#..#", "#.#..#", "#..#.#", "#..#.#", "#...##", "#...##", "#....#"]),
+    ('P', ["#####.", "#....#", "#....#", "#....#", "#####.", "#.....", "#.....", "#.....", "#.....", "#....."]),
+    ('R', ["#####.", "#....#", "#....#", "#....#", "#####.", "#..#..", "#...#.", "#...#.", "#....#", "#....#"]),
+    ('X', ["#....#", "#....#", ".#..#.", ".#..#.", "..##..", "..##..", ".#..#.", ".#..#.", "#....#", "#....#"]),
+    ('Z', ["######", ".....#", ".....#", "....#.", "...#..", "..#...", ".#....", "#.....", "#.....", "######"]),
+];
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum OcrError {
+    /// Only 6 (small font) and 10 (large font) rows are supported
+    UnsupportedHeight(usize),
+    /// The glyph at `index` (left to right) isn't a known letter, drawn as rows of '#'/'.'
+    UnknownGlyph { index: usize, glyph: Vec<String> },
+}
+
+impl fmt::Display for OcrError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            OcrError::UnsupportedHeight(height) => {
+                write!(f, "no font is {height} rows high (only 6 or 10)")
+            }
+            OcrError::UnknownGlyph { index, glyph } => {
+                writeln!(f, "unknown glyph at position {index}:")?;
+                write!(f, "{}", glyph.join("\n"))
+            }
+        }
+    }
+}
+
+impl Error for OcrError {}
+
+/// Reads the letters drawn in `grid`, where `is_lit` tells which cells are drawn
+pub fn recognize<G, F>(grid: &G, mut is_lit: F) -> Result<String, OcrError>
+where
+    G: Grid + ?Sized,
+    F: FnMut(&G::Item) -> bool,
+{
+    let (first_x, first_y) = grid.first_cell_coord();
+    let (last_x, last_y) = grid.last_cell_coord();
+    let rows: Vec<Vec<bool>> = (first_y..=last_y)
+        .map(|y| {
+            (first_x..=last_x)
+                .map(|x| grid.get_cell(x, y).is_some_and(&mut is_lit))
+                .collect()
+        })
+        .collect();
+    recognize_rows(&rows)
+}
+
+/// Reads the letters drawn as lines of '#' (lit) and any other char (unlit)
+pub fn recognize_str(s: &str) -> Result<String, OcrError> {
+    let rows: Vec<Vec<bool>> = s
+        .lines()
+        .filter(|l| !l.is_empty())
+        .map(|l| l.chars().map(|c| c == '#').collect())
+        .collect();
+    recognize_rows(&rows)
+}
+
+fn recognize_rows(rows: &[Vec<bool>]) -> Result<String, OcrError> {
+    let font: Vec<(char, Vec<String>)> = match rows.len() {
+        6 => SMALL_FONT
+            .iter()
+            .map(|(c, g)| (*c, trim_glyph(g.iter().map(|r| r.to_string()).collect())))
+            .collect(),
+        10 => LARGE_FONT
+            .iter()
+            .map(|(c, g)| (*c, trim_glyph(g.iter().map(|r| r.to_string()).collect())))
+            .collect(),
+        height => return Err(OcrError::UnsupportedHeight(height)),
+    };
+
+    // Letters are split by columns with nothing lit
+    let width = rows.iter().map(Vec::len).max().unwrap_or(0);
+    let lit = |x: usize, y: usize| rows[y].get(x).copied().unwrap_or(false);
+    let column_lit = |x: usize| (0..rows.len()).any(|y| lit(x, y));
+
+    let mut text = String::new();
+    let mut x = 0;
+    while x < width {
+        if !column_lit(x) {
+            x += 1;
+            continue;
+        }
+        let start = x;
+        while x < width && column_lit(x) {
+            x += 1;
+        }
+        let glyph: Vec<String> = (0..rows.len())
+            .map(|y| {
+                (start..x)
+                    .map(|gx| if lit(gx, y) { '#' } else { '.' })
+                    .collect()
+            })
+            .collect();
+        match font.iter().find(|(_, g)| *g == glyph) {
+            Some((c, _)) => text.push(*c),
+            None => {
+                return Err(OcrError::UnknownGlyph {
+                    index: text.len(),
+                    glyph,
+                })
+            }
+        }
+    }
+    Ok(text)
+}
+
+/// Removes the blank columns on either side of a glyph
+fn trim_glyph(glyph: Vec<String>) -> Vec<String> {
+    let lit_cols: Vec<usize> = glyph
+        .iter()
+        .flat_map(|row| {
+            row.char_indices()
+                .filter(|(_, c)| *c == '#')
+                .map(|(x, _)| x)
+        })
+        .collect();
+    let start = lit_cols.iter().min().copied().unwrap_or(0);
+    let end = lit_cols.iter().max().copied().unwrap_or(0);
+    glyph
+        .iter()
+        .map(|row| row[start..=end].to_string())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::StaticGrid;
+
+    use super::{recognize, recognize_str, OcrError, LARGE_FONT, SMALL_FONT};
+
+    #[test]
+    fn test_small_font() {
+        let display = "
+###..####.#..#.####..##....##..##..###..
+#..#....#.#..#.#....#..#....#.#..#.#..#.
+#..#...#..####.###..#.......#.#....###..
+###...#...#..#.#....#.##....#.#....#..#.
+#.#..#....#..#.#....#..#.#..#.#..#.#..#.
+#..#.####.#..#.#.....###..##...##..###..";
+        assert_eq!(recognize_str(display), Ok("RZHFGJCB".to_string()));
+
+        // Drawn by hand rather than from the table, so a wrong entry can't match itself
+        let display = "
+#..#.####.#...#
+#..#.#....#...#
+####.###...#.#.
+#..#.#......#..
+#..#.#......#..
+#..#.####...#..";
+        assert_eq!(recognize_str(display), Ok("HEY".to_string()));
+
+        // Every letter, from a grid
+        let mut grid = StaticGrid::<bool>::new(6, SMALL_FONT.len() * 6);
+        for (ndx, (_, glyph)) in SMALL_FONT.iter().enumerate() {
+            for (y, row) in glyph.iter().enumerate() {
+                for (x, c) in row.chars().enumerate() {
+                    grid.row_mut(y)[ndx * 6 + x] = c == '#';
+                }
+            }
+        }
+        let expected: String = SMALL_FONT.iter().map(|(c, _)| c).collect();
+        assert_eq!(recognize(&grid, |&lit| lit), Ok(expected));
+    }
+
+    #[test]
+    fn test_large_font() {
+        let display: String = (0..10)
+            .map(|y| {
+                LARGE_FONT
+                    .iter()
+                    .map(|(_, glyph)| format!("{}..", glyph[y]))
+                    .collect::<String>()
+                    + "\n"
+            })
+            .collect();
+        let expected: String = LARGE_FONT.iter().map(|(c, _)| c).collect();
+        assert_eq!(recognize_str(&display), Ok(expected));
+    }
+
+    #[test]
+    fn test_errors() {
+        assert_eq!(
+            recognize_str("#\n#\n#"),
+            Err(OcrError::UnsupportedHeight(3))
+        );
+        let Err(OcrError::UnknownGlyph { index, .. }) =
+            recognize_str("####.#\n#..#.#\n#..#.#\n#..#.#\n#..#.#\n####.#")
+        else {
+            panic!("Expected an unknown glyph");
+        };
+        assert_eq!(index, 0);
+    }
+}
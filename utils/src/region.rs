@@ -0,0 +1,225 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use crate::{Grid, StaticGrid};
+
+// North, South, East, West
+const NEIGHBOURS: [(isize, isize); 4] = [(0, -1), (0, 1), (1, 0), (-1, 0)];
+
+fn neighbours(x: isize, y: isize) -> impl Iterator<Item = (isize, isize)> {
+    NEIGHBOURS.iter().map(move |(dx, dy)| (x + dx, y + dy))
+}
+
+/// Breadth-first search from `start`, returning the number of steps to every reachable cell
+///
+/// `can_move(from, to)` decides if a step between two neighbouring cells is allowed
+pub fn reachable_distances<G, F>(
+    grid: &G,
+    start: (isize, isize),
+    mut can_move: F,
+) -> HashMap<(isize, isize), usize>
+where
+    G: Grid + ?Sized,
+    F: FnMut(&G::Item, &G::Item) -> bool,
+{
+    let mut distances = HashMap::new();
+    if grid.get_cell(start.0, start.1).is_none() {
+        return distances;
+    }
+    let mut queue = VecDeque::from([(start, 0)]);
+    distances.insert(start, 0);
+    while let Some(((x, y), distance)) = queue.pop_front() {
+        let from = grid.get_cell(x, y).unwrap();
+        for (nx, ny) in neighbours(x, y) {
+            if distances.contains_key(&(nx, ny)) {
+                continue;
+            }
+            let Some(to) = grid.get_cell(nx, ny) else {
+                continue;
+            };
+            if can_move(from, to) {
+                distances.insert((nx, ny), distance + 1);
+                queue.push_back(((nx, ny), distance + 1));
+            }
+        }
+    }
+    distances
+}
+
+/// All cells that can be reached from `start`
+pub fn reachable<G, F>(grid: &G, start: (isize, isize), can_move: F) -> HashSet<(isize, isize)>
+where
+    G: Grid + ?Sized,
+    F: FnMut(&G::Item, &G::Item) -> bool,
+{
+    reachable_distances(grid, start, can_move)
+        .into_keys()
+        .collect()
+}
+
+/// The connected cells around `seed` that all match `predicate`
+pub fn flood_fill<G, F>(grid: &G, seed: (isize, isize), mut predicate: F) -> HashSet<(isize, isize)>
+where
+    G: Grid + ?Sized,
+    F: FnMut(&G::Item) -> bool,
+{
+    match grid.get_cell(seed.0, seed.1) {
+        Some(c) if predicate(c) => reachable(grid, seed, |_, to| predicate(to)),
+        _ => HashSet::new(),
+    }
+}
+
+/// Flood fills from `seed`, calling `paint` on every filled cell. Returns the number of cells painted.
+pub fn flood_fill_mut<G, F, P>(
+    grid: &mut G,
+    seed: (isize, isize),
+    predicate: F,
+    mut paint: P,
+) -> usize
+where
+    G: Grid + ?Sized,
+    F: FnMut(&G::Item) -> bool,
+    P: FnMut(&mut G::Item),
+{
+    let cells = flood_fill(grid, seed, predicate);
+    for &(x, y) in &cells {
+        paint(grid.get_cell_mut(x, y).unwrap());
+    }
+    cells.len()
+}
+
+/// Number of cells in the region
+pub fn area(cells: &HashSet<(isize, isize)>) -> usize {
+    cells.len()
+}
+
+/// Number of cell edges that border something outside of the region
+pub fn perimeter(cells: &HashSet<(isize, isize)>) -> usize {
+    cells
+        .iter()
+        .map(|&(x, y)| neighbours(x, y).filter(|n| !cells.contains(n)).count())
+        .sum()
+}
+
+/// Connected-component labelling of a grid
+pub struct Regions {
+    /// Region id of every cell, indexed relative to `origin`
+    pub labels: StaticGrid<usize>,
+    pub num_regions: usize,
+    /// The grid coordinate of labels' (0, 0)
+    pub origin: (isize, isize),
+}
+
+impl Regions {
+    /// Labels every cell; neighbours share a region when `same_region(a, b)` is true
+    pub fn label<G, F>(grid: &G, mut same_region: F) -> Self
+    where
+        G: Grid + ?Sized,
+        F: FnMut(&G::Item, &G::Item) -> bool,
+    {
+        let origin = grid.first_cell_coord();
+        let last = grid.last_cell_coord();
+        let num_cols = (last.0 - origin.0 + 1) as usize;
+        let num_rows = (last.1 - origin.1 + 1) as usize;
+        let mut labels = StaticGrid::new(num_rows, num_cols);
+        let mut visited = StaticGrid::<bool>::new(num_rows, num_cols);
+        let mut num_regions = 0;
+
+        for y in 0..num_rows as isize {
+            for x in 0..num_cols as isize {
+                if *visited.get_cell(x, y).unwrap() {
+                    continue;
+                }
+                let region = reachable(grid, (x + origin.0, y + origin.1), &mut same_region);
+                for (rx, ry) in region {
+                    *labels.get_cell_mut(rx - origin.0, ry - origin.1).unwrap() = num_regions;
+                    *visited.get_cell_mut(rx - origin.0, ry - origin.1).unwrap() = true;
+                }
+                num_regions += 1;
+            }
+        }
+
+        Regions {
+            labels,
+            num_regions,
+            origin,
+        }
+    }
+
+    /// Region id of the cell at the grid coordinate x,y
+    pub fn region_at(&self, x: isize, y: isize) -> Option<usize> {
+        self.labels
+            .get_cell(x - self.origin.0, y - self.origin.1)
+            .copied()
+    }
+
+    /// Grid coordinates of every cell in the region
+    pub fn cells(&self, region: usize) -> HashSet<(isize, isize)> {
+        (0..self.labels.num_rows as isize)
+            .flat_map(|y| (0..self.labels.num_cols as isize).map(move |x| (x, y)))
+            .filter(|&(x, y)| *self.labels.get_cell(x, y).unwrap() == region)
+            .map(|(x, y)| (x + self.origin.0, y + self.origin.1))
+            .collect()
+    }
+
+    pub fn area(&self, region: usize) -> usize {
+        self.labels.cell_iter().filter(|&&r| r == region).count()
+    }
+
+    pub fn perimeter(&self, region: usize) -> usize {
+        perimeter(&self.cells(region))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Grid, StaticGrid};
+
+    use super::{flood_fill, flood_fill_mut, perimeter, reachable_distances, Regions};
+
+    fn grid_from(s: &str) -> StaticGrid<char> {
+        let rows: Vec<&str> = s.lines().collect();
+        StaticGrid {
+            cells: rows.iter().flat_map(|r| r.chars()).collect(),
+            num_rows: rows.len(),
+            num_cols: rows[0].len(),
+        }
+    }
+
+    #[test]
+    fn test_flood_fill() {
+        let mut g = grid_from("AAB\nABB\nAAB");
+        let region = flood_fill(&g, (0, 0), |&c| c == 'A');
+        assert_eq!(region.len(), 5);
+        assert_eq!(perimeter(&region), 12);
+
+        let painted = flood_fill_mut(&mut g, (2, 0), |&c| c == 'B', |c| *c = 'C');
+        assert_eq!(painted, 4);
+        assert_eq!(*g.get_cell(1, 1).unwrap(), 'C');
+        assert!(flood_fill(&g, (0, 0), |&c| c == 'B').is_empty());
+    }
+
+    #[test]
+    fn test_reachable_distances() {
+        let g = grid_from("...\n.#.\n...");
+        let distances = reachable_distances(&g, (0, 0), |_, &to| to != '#');
+        assert_eq!(distances.len(), 8);
+        assert_eq!(distances[&(2, 2)], 4);
+        assert!(!distances.contains_key(&(1, 1)));
+    }
+
+    #[test]
+    fn test_regions() {
+        let g = grid_from("AAAA\nBBCD\nBBCC\nEEEC");
+        let regions = Regions::label(&g, |a, b| a == b);
+        assert_eq!(regions.num_regions, 5);
+
+        let c = regions.region_at(2, 1).unwrap();
+        assert_eq!(regions.area(c), 4);
+        assert_eq!(regions.perimeter(c), 10);
+
+        let a = regions.region_at(0, 0).unwrap();
+        assert_eq!(regions.area(a), 4);
+        assert_eq!(regions.perimeter(a), 10);
+        assert_eq!(regions.region_at(4, 0), None);
+    }
+}
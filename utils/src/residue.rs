@@ -0,0 +1,164 @@
+use std::rc::Rc;
+
+use crate::expr::{Arithmetic, BinOp, EvalError};
+
+/// A number kept only as its remainders for a fixed set of moduli, so it never grows.
+/// Supports + and *, and % by anything that divides one of the moduli. - and / only work
+/// on exact numbers.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Residues {
+    /// Not tied to any moduli yet (e.g. a constant in an expression)
+    Exact(u64),
+    Reduced { moduli: Rc<[u64]>, values: Vec<u64> },
+}
+
+impl Residues {
+    pub fn new(value: u64, moduli: Rc<[u64]>) -> Self {
+        assert!(!moduli.contains(&0), "Moduli must be non-zero");
+        let values = moduli.iter().map(|m| value % m).collect();
+        Residues::Reduced { moduli, values }
+    }
+
+    /// The number mod `modulus`, if it can be known
+    pub fn residue(&self, modulus: u64) -> Option<u64> {
+        match self {
+            Residues::Exact(value) => value.checked_rem(modulus),
+            Residues::Reduced { moduli, values } => moduli
+                .iter()
+                .zip(values)
+                .find(|(m, _)| modulus != 0 && *m % modulus == 0)
+                .map(|(_, v)| v % modulus),
+        }
+    }
+
+    // Applies `exact` if both are exact, otherwise `reduced(a, b, modulus)` residue by residue
+    fn combine(
+        &self,
+        rhs: &Self,
+        op: BinOp,
+        exact: fn(u64, u64) -> Option<u64>,
+        reduced: fn(u128, u128, u128) -> u128,
+    ) -> Result<Self, EvalError> {
+        let moduli = match (self, rhs) {
+            (Residues::Exact(a), Residues::Exact(b)) => {
+                return exact(*a, *b).map(Residues::Exact).ok_or(EvalError::Overflow)
+            }
+            (Residues::Reduced { moduli, .. }, Residues::Exact(_))
+            | (Residues::Exact(_), Residues::Reduced { moduli, .. }) => moduli,
+            (Residues::Reduced { moduli: a, .. }, Residues::Reduced { moduli: b, .. }) => {
+                if a != b {
+                    return Err(EvalError::Unsupported(op));
+                }
+                a
+            }
+        };
+        let residues = |r: &Self| match r {
+            Residues::Exact(value) => moduli.iter().map(|m| value % m).collect(),
+            Residues::Reduced { values, .. } => values.clone(),
+        };
+        let values = moduli
+            .iter()
+            .zip(residues(self).into_iter().zip(residues(rhs)))
+            .map(|(&m, (a, b))| reduced(a as u128, b as u128, m as u128) as u64)
+            .collect();
+        Ok(Residues::Reduced {
+            moduli: moduli.clone(),
+            values,
+        })
+    }
+}
+
+impl Arithmetic for Residues {
+    fn constant(value: u64) -> Result<Self, EvalError> {
+        Ok(Residues::Exact(value))
+    }
+
+    fn add(&self, rhs: &Self) -> Result<Self, EvalError> {
+        self.combine(rhs, BinOp::Add, u64::checked_add, |a, b, m| (a + b) % m)
+    }
+
+    fn sub(&self, rhs: &Self) -> Result<Self, EvalError> {
+        // Only exact numbers know whether they'd go negative, and wrapping around the moduli
+        // would hide the overflow the other worry types report
+        match (self, rhs) {
+            (Residues::Exact(a), Residues::Exact(b)) => {
+                a.checked_sub(*b).map(Residues::Exact).ok_or(EvalError::Overflow)
+            }
+            _ => Err(EvalError::Unsupported(BinOp::Sub)),
+        }
+    }
+
+    fn mul(&self, rhs: &Self) -> Result<Self, EvalError> {
+        self.combine(rhs, BinOp::Mul, u64::checked_mul, |a, b, m| (a * b) % m)
+    }
+
+    fn div(&self, rhs: &Self) -> Result<Self, EvalError> {
+        match (self, rhs) {
+            (_, Residues::Exact(0)) => Err(EvalError::DivideByZero),
+            (Residues::Exact(a), Residues::Exact(b)) => Ok(Residues::Exact(a / b)),
+            _ => Err(EvalError::Unsupported(BinOp::Div)),
+        }
+    }
+
+    fn rem(&self, rhs: &Self) -> Result<Self, EvalError> {
+        match rhs {
+            Residues::Exact(0) => Err(EvalError::DivideByZero),
+            Residues::Exact(modulus) => self
+                .residue(*modulus)
+                .map(Residues::Exact)
+                .ok_or(EvalError::Unsupported(BinOp::Rem)),
+            Residues::Reduced { .. } => Err(EvalError::Unsupported(BinOp::Rem)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::expr::{Arithmetic, BinOp, EvalError, Expr};
+
+    use super::Residues;
+
+    #[test]
+    fn test_residues() {
+        let moduli: std::rc::Rc<[u64]> = vec![13, 17, 19, 23].into();
+        let expr = Expr::parse("old * old + 6").unwrap();
+        let mut exact: u64 = 79;
+        let mut residues = Residues::new(exact, moduli.clone());
+        for _ in 0..3 {
+            exact = expr.eval(&|_| Some(exact)).unwrap();
+            residues = expr.eval(&|_| Some(residues.clone())).unwrap();
+        }
+        for m in [13, 17, 19, 23, 1] {
+            assert_eq!(residues.residue(m), Some(exact % m));
+        }
+        assert_eq!(
+            residues.rem(&Residues::Exact(23)),
+            Ok(Residues::Exact(exact % 23))
+        );
+        assert_eq!(residues.residue(7), None);
+        assert_eq!(
+            residues.div(&Residues::Exact(3)),
+            Err(EvalError::Unsupported(BinOp::Div))
+        );
+        assert_eq!(
+            residues.add(&Residues::new(1, vec![2].into())),
+            Err(EvalError::Unsupported(BinOp::Add))
+        );
+        assert_eq!(
+            Residues::Exact(u64::MAX).add(&Residues::Exact(1)),
+            Err(EvalError::Overflow)
+        );
+        assert_eq!(
+            residues.sub(&Residues::Exact(1)),
+            Err(EvalError::Unsupported(BinOp::Sub))
+        );
+        assert_eq!(
+            Residues::Exact(5).sub(&Residues::Exact(3)),
+            Ok(Residues::Exact(2))
+        );
+        assert_eq!(
+            Residues::Exact(3).sub(&Residues::Exact(5)),
+            Err(EvalError::Overflow)
+        );
+    }
+}